@@ -0,0 +1,397 @@
+use crate::game::{CellContent, CellState, ClickStats, Minesweeper};
+
+/// Difficulty metrics of a mine layout, as used on competitive boards.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BoardStats {
+    /// Bechtel's Board Benchmark Value: the fewest left clicks that clear
+    /// the board without flags or chords.
+    pub bbbv: u32,
+    /// Connected areas of empty cells; one click opens each.
+    pub openings: u32,
+    /// Connected groups of numbers that no opening reveals.
+    pub islands: u32,
+    /// Greedy estimate of the fewest clicks when flagging and chording are
+    /// allowed. Never more than `bbbv`.
+    pub zini: u32,
+}
+
+/// How well a game was played, from its clicks and the 3BV it cleared.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Performance {
+    pub bbbv: u32,
+    /// 3BV of the openings and isolated numbers actually revealed.
+    pub solved_bbbv: u32,
+    pub elapsed_ms: u64,
+    pub clicks: ClickStats,
+}
+
+impl Performance {
+    pub fn seconds(&self) -> f32 {
+        self.elapsed_ms as f32 / 1000.0
+    }
+
+    pub fn bbbv_per_second(&self) -> f32 {
+        self.solved_bbbv as f32 * 1000.0 / self.elapsed_ms.max(1) as f32
+    }
+
+    /// Index of efficiency: 3BV solved per click.
+    pub fn ioe(&self) -> f32 {
+        ratio(self.solved_bbbv, self.clicks.total())
+    }
+
+    /// 3BV solved per useful click.
+    pub fn throughput(&self) -> f32 {
+        ratio(self.solved_bbbv, self.clicks.useful())
+    }
+
+    /// Share of clicks that changed the board.
+    pub fn correctness(&self) -> f32 {
+        ratio(self.clicks.useful(), self.clicks.total())
+    }
+}
+
+fn ratio(part: u32, whole: u32) -> f32 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f32 / whole as f32
+    }
+}
+
+// The parts of a layout the metrics look at
+struct Layout {
+    mine: Vec<u8>, // Mines in each cell
+    number: Vec<u8>,
+    neighbors: Vec<Vec<usize>>,
+    opening: Vec<Option<usize>>, // Which opening an empty cell belongs to
+    openings: Vec<Vec<usize>>,   // Empty cells of each opening
+    border: Vec<bool>,           // Numbers revealed by some opening
+}
+
+impl Layout {
+    fn new(game: &Minesweeper) -> Self {
+        let len = game.cells.len();
+        let mut neighbors = Vec::with_capacity(len);
+        for r in 0..game.rows {
+            for c in 0..game.cols {
+                neighbors.push(
+                    game.neighbors(r, c)
+                        .into_iter()
+                        .map(|(nr, nc)| game.index(nr, nc))
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+        let mine: Vec<u8> = game.cells.iter().map(|cell| cell.content.mines()).collect();
+        let number: Vec<u8> = game
+            .cells
+            .iter()
+            .map(|cell| match cell.content {
+                CellContent::Number(n) => n,
+                _ => 0,
+            })
+            .collect();
+        let is_empty = |idx: usize| mine[idx] == 0 && number[idx] == 0;
+
+        let mut opening = vec![None; len];
+        let mut openings = Vec::new();
+        for start in 0..len {
+            if !is_empty(start) || opening[start].is_some() {
+                continue;
+            }
+            let id = openings.len();
+            let mut cells = Vec::new();
+            let mut stack = vec![start];
+            opening[start] = Some(id);
+            while let Some(idx) = stack.pop() {
+                cells.push(idx);
+                for &n in &neighbors[idx] {
+                    if is_empty(n) && opening[n].is_none() {
+                        opening[n] = Some(id);
+                        stack.push(n);
+                    }
+                }
+            }
+            openings.push(cells);
+        }
+
+        let border = (0..len)
+            .map(|idx| number[idx] > 0 && neighbors[idx].iter().any(|&n| is_empty(n)))
+            .collect();
+
+        Self {
+            mine,
+            number,
+            neighbors,
+            opening,
+            openings,
+            border,
+        }
+    }
+
+    // A number that needs a click of its own when only left clicks are used
+    fn isolated(&self, idx: usize) -> bool {
+        self.number[idx] > 0 && !self.border[idx]
+    }
+
+    fn islands(&self) -> u32 {
+        let mut seen = vec![false; self.mine.len()];
+        let mut islands = 0;
+        for start in 0..self.mine.len() {
+            if !self.isolated(start) || seen[start] {
+                continue;
+            }
+            islands += 1;
+            let mut stack = vec![start];
+            seen[start] = true;
+            while let Some(idx) = stack.pop() {
+                for &n in &self.neighbors[idx] {
+                    if self.isolated(n) && !seen[n] {
+                        seen[n] = true;
+                        stack.push(n);
+                    }
+                }
+            }
+        }
+        islands
+    }
+}
+
+// Board state while estimating ZiNi
+struct Sweep<'a> {
+    layout: &'a Layout,
+    revealed: Vec<bool>,
+    flagged: Vec<bool>,
+    opened: Vec<bool>,
+}
+
+impl Sweep<'_> {
+    // Reveals a cell, flooding an opening, and notes every cell that changed
+    fn reveal(&mut self, idx: usize, changed: &mut Vec<usize>) {
+        if self.revealed[idx] {
+            return;
+        }
+        let Some(id) = self.layout.opening[idx] else {
+            self.revealed[idx] = true;
+            changed.push(idx);
+            return;
+        };
+        self.opened[id] = true;
+        for &cell in &self.layout.openings[id] {
+            for &n in std::iter::once(&cell).chain(&self.layout.neighbors[cell]) {
+                if !self.revealed[n] {
+                    self.revealed[n] = true;
+                    changed.push(n);
+                }
+            }
+        }
+    }
+
+    // Clicks spent on a chord: opening the number, missing flags (one per
+    // mine) and the chord itself
+    fn cost(&self, idx: usize) -> u32 {
+        let layout = self.layout;
+        let flags: u32 = layout.neighbors[idx]
+            .iter()
+            .filter(|&&n| !self.flagged[n])
+            .map(|&n| layout.mine[n] as u32)
+            .sum();
+        flags + 1 + !self.revealed[idx] as u32
+    }
+
+    // 3BV a chord on this number clears, less what it costs
+    fn premium(&self, idx: usize) -> Option<i32> {
+        let layout = self.layout;
+        if layout.number[idx] == 0 {
+            return None;
+        }
+        let mut gain = 0;
+        if !self.revealed[idx] {
+            gain += layout.isolated(idx) as i32;
+        }
+        let mut openings = Vec::new();
+        let mut hidden = false;
+        for &n in &layout.neighbors[idx] {
+            if layout.mine[n] == 0 && !self.revealed[n] {
+                hidden = true;
+                match layout.opening[n] {
+                    Some(id) if !self.opened[id] && !openings.contains(&id) => openings.push(id),
+                    Some(_) => {}
+                    None => gain += layout.isolated(n) as i32,
+                }
+            }
+        }
+        if !hidden {
+            return None;
+        }
+        Some(gain + openings.len() as i32 - self.cost(idx) as i32)
+    }
+}
+
+impl Minesweeper {
+    /// Metrics for the current mine layout, or `None` before the first click
+    /// has placed the mines.
+    pub fn analyze(&self) -> Option<BoardStats> {
+        if self.mine_layout().is_empty() {
+            return None;
+        }
+        let layout = Layout::new(self);
+        let len = layout.mine.len();
+        let isolated = (0..len).filter(|&idx| layout.isolated(idx)).count() as u32;
+        let openings = layout.openings.len() as u32;
+        let bbbv = openings + isolated;
+
+        // Greedy ZiNi: keep taking the chord that saves the most clicks, then
+        // click whatever is left one by one
+        let mut sweep = Sweep {
+            layout: &layout,
+            revealed: vec![false; len],
+            flagged: vec![false; len],
+            opened: vec![false; openings as usize],
+        };
+        let mut premiums: Vec<Option<i32>> = (0..len).map(|idx| sweep.premium(idx)).collect();
+        let mut clicks = 0;
+        let mut changed = Vec::new();
+        loop {
+            let best = premiums
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, p)| p.map(|p| (idx, p)))
+                .max_by_key(|&(idx, p)| (p, std::cmp::Reverse(idx)));
+            let Some((idx, _)) = best.filter(|&(_, p)| p > 0) else {
+                break;
+            };
+            clicks += sweep.cost(idx);
+
+            changed.clear();
+            sweep.reveal(idx, &mut changed);
+            for &n in &layout.neighbors[idx] {
+                if layout.mine[n] > 0 {
+                    if !sweep.flagged[n] {
+                        sweep.flagged[n] = true;
+                        changed.push(n);
+                    }
+                } else {
+                    sweep.reveal(n, &mut changed);
+                }
+            }
+            // A premium only depends on a cell and its neighbours
+            for &cell in &changed {
+                for &n in std::iter::once(&cell).chain(&layout.neighbors[cell]) {
+                    premiums[n] = sweep.premium(n);
+                }
+            }
+        }
+
+        let unopened = sweep.opened.iter().filter(|&&o| !o).count() as u32;
+        let unclicked = (0..len)
+            .filter(|&idx| layout.isolated(idx) && !sweep.revealed[idx])
+            .count() as u32;
+        let zini = (clicks + unopened + unclicked).min(bbbv);
+
+        Some(BoardStats {
+            bbbv,
+            openings,
+            islands: layout.islands(),
+            zini,
+        })
+    }
+
+    /// Click efficiency of the game so far, or `None` before the mines are
+    /// placed.
+    pub fn performance(&self) -> Option<Performance> {
+        let stats = self.analyze()?;
+        let layout = Layout::new(self);
+        // A wrong flag shown after a loss was never revealed by the player
+        let revealed = |idx: usize| {
+            let cell = &self.cells[idx];
+            cell.state == CellState::Revealed && !cell.wrong_flag
+        };
+        let opened = layout
+            .openings
+            .iter()
+            .filter(|cells| cells.iter().any(|&idx| revealed(idx)))
+            .count() as u32;
+        let clicked = (0..layout.mine.len())
+            .filter(|&idx| layout.isolated(idx) && revealed(idx))
+            .count() as u32;
+        Some(Performance {
+            bbbv: stats.bbbv,
+            solved_bbbv: opened + clicked,
+            elapsed_ms: self.elapsed_ms(),
+            clicks: self.clicks,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Difficulty;
+
+    // A game laid out from rows of `*` for a mine and `.` for a safe cell
+    fn game(rows: &[&str]) -> Minesweeper {
+        let cells: Vec<char> = rows.iter().flat_map(|row| row.chars()).collect();
+        let mines: Vec<usize> = (0..cells.len()).filter(|&i| cells[i] == '*').collect();
+        let difficulty =
+            Difficulty::custom(rows.len() as u32, rows[0].len() as u32, mines.len() as u32);
+        let mut game = Minesweeper::new(difficulty.unwrap());
+        game.set_layout(&mines);
+        game
+    }
+
+    fn stats(bbbv: u32, openings: u32, islands: u32, zini: u32) -> Option<BoardStats> {
+        Some(BoardStats {
+            bbbv,
+            openings,
+            islands,
+            zini,
+        })
+    }
+
+    #[test]
+    fn nothing_to_analyze_before_the_mines_are_placed() {
+        let game = Minesweeper::new(Difficulty::Beginner);
+        assert_eq!(game.analyze(), None);
+        assert_eq!(game.performance(), None);
+    }
+
+    #[test]
+    fn one_opening_reveals_every_number() {
+        let game = game(&["*..", "...", "..."]);
+        assert_eq!(game.analyze(), stats(1, 1, 0, 1));
+    }
+
+    #[test]
+    fn numbers_no_opening_reaches_are_clicked_one_by_one() {
+        // M 1 1 M: two touching numbers make one island
+        assert_eq!(game(&["*..*"]).analyze(), stats(2, 0, 1, 2));
+        // The opening on the right shows the 1 beside it, but not the 2
+        // between the mines
+        assert_eq!(game(&["*.*..."]).analyze(), stats(2, 1, 1, 2));
+    }
+
+    #[test]
+    fn zini_chords_where_it_saves_clicks() {
+        // Twelve numbers with a mine in each corner. Chording the top-left
+        // inner 1 costs three clicks and clears eight numbers, and chording
+        // the bottom-right one costs two more and clears the other four.
+        let game = game(&["*..*", "....", "....", "*..*"]);
+        assert_eq!(game.analyze(), stats(12, 0, 1, 5));
+    }
+
+    #[test]
+    fn performance_counts_the_3bv_cleared() {
+        let mut game = game(&["*..*"]);
+        game.reveal(0, 1).unwrap();
+        game.toggle_flag(0, 1).unwrap();
+        let performance = game.performance().unwrap();
+        assert_eq!((performance.bbbv, performance.solved_bbbv), (2, 1));
+        assert_eq!(
+            (performance.clicks.total(), performance.clicks.useful()),
+            (2, 1)
+        );
+        assert_eq!(performance.ioe(), 0.5);
+        assert_eq!(performance.correctness(), 0.5);
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{FileError, FileFormat};
+
+/// Format version written to the bindings file.
+pub const BINDINGS_VERSION: u32 = 1;
+
+const FORMAT: FileFormat = FileFormat::new("bindings", BINDINGS_VERSION);
+
+/// What a mouse button does on the board.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ButtonAction {
+    Reveal,
+    Flag,
+    Chord,
+    Nothing,
+}
+
+/// A physical mouse button.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+/// Game actions that can be given keys.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum KeyAction {
+    Reveal, // The cell under the keyboard cursor, like the other two
+    Flag,
+    Chord,
+    NewGame,
+    Hint,
+    Undo,
+}
+
+/// Keys for each action, in keystroke notation such as `ctrl-z` or `f2`.
+/// An action can have any number of keys, or none.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub reveal: Vec<String>,
+    pub flag: Vec<String>,
+    pub chord: Vec<String>,
+    pub new_game: Vec<String>,
+    pub hint: Vec<String>,
+    pub undo: Vec<String>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let keys = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+        Self {
+            reveal: keys(&["space"]),
+            flag: keys(&["f"]),
+            chord: keys(&["d", "enter"]),
+            new_game: keys(&["f2"]),
+            hint: keys(&["ctrl-h"]),
+            undo: keys(&["ctrl-z"]),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// Every action with its keys.
+    pub fn actions(&self) -> [(KeyAction, &[String]); 6] {
+        [
+            (KeyAction::Reveal, &self.reveal),
+            (KeyAction::Flag, &self.flag),
+            (KeyAction::Chord, &self.chord),
+            (KeyAction::NewGame, &self.new_game),
+            (KeyAction::Hint, &self.hint),
+            (KeyAction::Undo, &self.undo),
+        ]
+    }
+}
+
+/// What each mouse button does. Pressing the reveal and flag buttons
+/// together, or double-clicking with the reveal button, chords as well.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseBindings {
+    pub left: ButtonAction,
+    pub right: ButtonAction,
+    pub middle: ButtonAction,
+    /// Swaps what the left and right buttons do.
+    pub left_handed: bool,
+    /// A reveal click on a number chords it, as many modern clients do.
+    pub left_click_chords: bool,
+}
+
+impl Default for MouseBindings {
+    fn default() -> Self {
+        Self {
+            left: ButtonAction::Reveal,
+            right: ButtonAction::Flag,
+            middle: ButtonAction::Chord,
+            left_handed: false,
+            left_click_chords: false,
+        }
+    }
+}
+
+impl MouseBindings {
+    pub fn action(&self, button: Button) -> ButtonAction {
+        match (button, self.left_handed) {
+            (Button::Left, false) | (Button::Right, true) => self.left,
+            (Button::Right, false) | (Button::Left, true) => self.right,
+            (Button::Middle, _) => self.middle,
+        }
+    }
+}
+
+/// The user's keys and mouse buttons. Anything left out of the file keeps
+/// its default.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bindings {
+    pub keys: KeyBindings,
+    pub mouse: MouseBindings,
+}
+
+/// Where the bindings are read from, e.g. `~/.local/share/minesweep/bindings.json`.
+pub fn bindings_path() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("bindings.json"))
+}
+
+impl Bindings {
+    pub fn to_json(&self) -> String {
+        FORMAT.to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, FileError> {
+        FORMAT.from_json(json)
+    }
+
+    /// Reads the bindings file; a missing file means the defaults.
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        FORMAT.load_or_default(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        FORMAT.save(path, self)
+    }
+}
@@ -0,0 +1,225 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Where a game reads the time from. Games use the system clock unless
+/// given another, e.g. a `ManualClock` in tests.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+}
+
+/// The real, monotonic time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct ManualClock {
+    start: Instant,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + *self.offset.lock().unwrap()
+    }
+}
+
+/// A stopwatch on a `Clock`. Saved as the milliseconds on it, whether or
+/// not it is running; it loads stopped.
+#[derive(Clone)]
+pub struct Timer {
+    clock: Arc<dyn Clock>,
+    banked: Duration,               // Time before `running_since`
+    running_since: Option<Instant>, // None while stopped
+}
+
+impl Timer {
+    pub fn new(elapsed: Duration) -> Self {
+        Self {
+            clock: system_clock(),
+            banked: elapsed,
+            running_since: None,
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    /// Swaps the clock, keeping the reading and whether it runs.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let running = self.is_running();
+        self.stop();
+        self.clock = clock;
+        if running {
+            self.start();
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_at(self.now())
+    }
+
+    pub fn elapsed_at(&self, now: Instant) -> Duration {
+        let running = self
+            .running_since
+            .map_or(Duration::ZERO, |since| now.saturating_duration_since(since));
+        self.banked + running
+    }
+
+    pub fn start(&mut self) {
+        self.start_at(self.now());
+    }
+
+    /// Starts counting from `now`, unless already running.
+    pub fn start_at(&mut self, now: Instant) {
+        self.running_since.get_or_insert(now);
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_at(self.now());
+    }
+
+    /// Stops counting as of `now`, keeping the time so far.
+    pub fn stop_at(&mut self, now: Instant) {
+        self.banked = self.elapsed_at(now);
+        self.running_since = None;
+    }
+
+    /// Sets the reading and stops the timer.
+    pub fn set(&mut self, elapsed: Duration) {
+        self.banked = elapsed;
+        self.running_since = None;
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new(Duration::ZERO)
+    }
+}
+
+impl fmt::Debug for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timer")
+            .field("elapsed", &self.elapsed())
+            .field("running", &self.is_running())
+            .finish()
+    }
+}
+
+impl Serialize for Timer {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(self.elapsed().as_millis() as u64)
+    }
+}
+
+impl<'de> Deserialize<'de> for Timer {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        u64::deserialize(deserializer).map(|ms| Self::new(Duration::from_millis(ms)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Difficulty, GameStatus, Minesweeper};
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn timer_counts_only_while_running() {
+        let clock = Arc::new(ManualClock::new());
+        let mut timer = Timer::default();
+        timer.set_clock(clock.clone());
+        clock.advance(SECOND);
+        assert_eq!(timer.elapsed(), Duration::ZERO);
+
+        timer.start();
+        clock.advance(Duration::from_millis(1500));
+        timer.stop();
+        clock.advance(SECOND);
+        assert_eq!(timer.elapsed(), Duration::from_millis(1500));
+
+        timer.start();
+        clock.advance(Duration::from_millis(250));
+        assert_eq!(timer.elapsed(), Duration::from_millis(1750));
+    }
+
+    #[test]
+    fn game_time_leaves_out_pauses_and_stops_at_the_win() {
+        // M 1 1 M: one reveal starts the game, the other wins it
+        let mut game = Minesweeper::new(Difficulty::custom(1, 4, 2).unwrap());
+        game.set_layout(&[0, 3]);
+        let clock = Arc::new(ManualClock::new());
+        game.set_clock(clock.clone());
+
+        clock.advance(SECOND);
+        game.reveal(0, 1).unwrap();
+        assert_eq!(game.status, GameStatus::Playing);
+        clock.advance(Duration::from_millis(1200));
+
+        game.pause();
+        assert!(game.is_paused());
+        clock.advance(Duration::from_secs(60));
+        game.resume();
+        clock.advance(Duration::from_millis(300));
+
+        game.reveal(0, 2).unwrap();
+        assert_eq!(game.status, GameStatus::Won);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(game.elapsed_ms(), 1500);
+    }
+
+    #[test]
+    fn a_move_while_paused_restarts_the_clock() {
+        let mut game = Minesweeper::new(Difficulty::custom(1, 4, 2).unwrap());
+        game.set_layout(&[0, 3]);
+        let clock = Arc::new(ManualClock::new());
+        game.set_clock(clock.clone());
+
+        game.reveal(0, 1).unwrap();
+        clock.advance(SECOND);
+        game.pause();
+        clock.advance(Duration::from_secs(10));
+        game.toggle_flag(0, 0).unwrap();
+        assert!(!game.is_paused());
+        clock.advance(SECOND);
+        assert_eq!(game.elapsed_ms(), 2000);
+    }
+}
@@ -0,0 +1,64 @@
+use gpui::*;
+
+use crate::MinesweeperView;
+
+// Keyboard play: a cursor cell moved with the arrows or hjkl, acted on like
+// the mouse would. It appears with the first key press.
+impl MinesweeperView {
+    pub fn move_cursor(&mut self, d_row: i32, d_col: i32, cx: &mut Context<Self>) {
+        let (rows, cols) = (self.game.rows as i32, self.game.cols as i32);
+        let Some((row, col)) = self.cursor else {
+            self.cursor = Some((self.game.rows / 2, self.game.cols / 2));
+            cx.notify();
+            return;
+        };
+        let (row, col) = (row as i32 + d_row, col as i32 + d_col);
+        // Wrap-around boards let the cursor go round too
+        let (row, col) = if self.game.wrap {
+            (row.rem_euclid(rows), col.rem_euclid(cols))
+        } else {
+            (row.clamp(0, rows - 1), col.clamp(0, cols - 1))
+        };
+        self.cursor = Some((row as u32, col as u32));
+        cx.notify();
+    }
+
+    // The cursor cell, unless play is on hold
+    fn cursor_target(&self) -> Option<(u32, u32)> {
+        if self.is_paused() {
+            return None;
+        }
+        self.cursor
+            .filter(|&(row, col)| self.game.contains(row, col))
+    }
+
+    pub fn cursor_reveal(&mut self, cx: &mut Context<Self>) {
+        if let Some((row, col)) = self.cursor_target() {
+            self.handle_click(row, col, cx);
+        }
+    }
+
+    pub fn cursor_flag(&mut self, cx: &mut Context<Self>) {
+        if let Some((row, col)) = self.cursor_target() {
+            self.handle_right_click(row, col, cx);
+        }
+    }
+
+    pub fn cursor_chord(&mut self, cx: &mut Context<Self>) {
+        if let Some((row, col)) = self.cursor_target() {
+            self.handle_chord_start(row, col, cx);
+            self.handle_chord_end(row, col, cx);
+        }
+    }
+}
+
+// Frame drawn around the cursor cell
+pub fn cursor_overlay(color: Rgba) -> impl IntoElement {
+    div()
+        .absolute()
+        .top_0()
+        .left_0()
+        .size_full()
+        .border_2()
+        .border_color(color)
+}
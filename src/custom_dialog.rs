@@ -0,0 +1,124 @@
+use gpui::*;
+
+use crate::text_field::TextField;
+use crate::theme::theme;
+use crate::{MinesweeperView, bevel_button};
+use minesweep::game::{Difficulty, MAX_DIMENSION};
+
+// "Custom Field" dialog, modelled on the Win2000 one: height, width and mine count.
+pub struct CustomDialog {
+    rows: Entity<TextField>,
+    cols: Entity<TextField>,
+    mines: Entity<TextField>,
+    error: Option<SharedString>,
+    game_window: WindowHandle<MinesweeperView>,
+}
+
+impl CustomDialog {
+    pub fn new(
+        current: Difficulty,
+        game_window: WindowHandle<MinesweeperView>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let (rows, cols, mines) = current.config();
+        let digits = MAX_DIMENSION.to_string().len();
+        let rows = cx.new(|cx| TextField::numeric(rows, digits, cx));
+        let cols = cx.new(|cx| TextField::numeric(cols, digits, cx));
+        let mines = cx.new(|cx| TextField::numeric(mines, digits * 2, cx));
+        rows.read(cx).focus_handle(cx).focus(window);
+        Self {
+            rows,
+            cols,
+            mines,
+            error: None,
+            game_window,
+        }
+    }
+
+    fn parse(field: &Entity<TextField>, cx: &App) -> u32 {
+        // Empty or overflowing input turns into 0 and is rejected by validation
+        field.read(cx).text().parse().unwrap_or(0)
+    }
+
+    fn confirm(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let rows = Self::parse(&self.rows, cx);
+        let cols = Self::parse(&self.cols, cx);
+        let mines = Self::parse(&self.mines, cx);
+        match Difficulty::custom(rows, cols, mines) {
+            Ok(difficulty) => {
+                self.game_window
+                    .update(cx, |view, window, cx| view.reset(difficulty, window, cx))
+                    .ok();
+                window.remove_window();
+            }
+            Err(err) => {
+                self.error = Some(err.to_string().into());
+                cx.notify();
+            }
+        }
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event.keystroke.key.as_str() {
+            "enter" => self.confirm(window, cx),
+            "escape" => window.remove_window(),
+            "tab" if event.keystroke.modifiers.shift => window.focus_prev(),
+            "tab" => window.focus_next(),
+            _ => {}
+        }
+    }
+}
+
+fn labeled_field(label: &'static str, field: &Entity<TextField>) -> Div {
+    div()
+        .flex()
+        .items_center()
+        .justify_between()
+        .child(div().text_size(px(12.0)).child(label))
+        .child(field.clone())
+}
+
+impl Render for CustomDialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = theme(cx).clone();
+        div()
+            .on_key_down(cx.listener(Self::handle_key_down))
+            .size_full()
+            .bg(theme.face)
+            .text_color(theme.text)
+            .p(px(10.0))
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .child(labeled_field("Height:", &self.rows))
+            .child(labeled_field("Width:", &self.cols))
+            .child(labeled_field("Mines:", &self.mines))
+            .child(
+                div()
+                    .h(px(14.0))
+                    .text_size(px(11.0))
+                    .text_color(theme.error_text)
+                    .children(self.error.clone()),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .gap(px(6.0))
+                    .child(bevel_button(&theme, "OK").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _, window, cx| view.confirm(window, cx)),
+                    ))
+                    .child(bevel_button(&theme, "Cancel").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|_, _, window, _| window.remove_window()),
+                    )),
+            )
+    }
+}
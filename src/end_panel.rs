@@ -0,0 +1,63 @@
+use gpui::*;
+
+use crate::theme::theme;
+use crate::{MinesweeperView, bevel_raised};
+use minesweep::analysis::Performance;
+
+// Summary laid over the board once a game is won or lost; a click puts it away
+impl MinesweeperView {
+    pub fn render_end_panel(&self, performance: Performance, cx: &mut Context<Self>) -> Div {
+        let clicks = performance.clicks;
+        let lines = [
+            ("Time", format!("{:.3}s", performance.seconds())),
+            (
+                "3BV",
+                format!("{}/{}", performance.solved_bbbv, performance.bbbv),
+            ),
+            ("3BV/s", format!("{:.2}", performance.bbbv_per_second())),
+            (
+                "Clicks",
+                format!("{} ({} wasted)", clicks.total(), clicks.wasted()),
+            ),
+            ("IOE", format!("{:.2}", performance.ioe())),
+            ("Throughput", format!("{:.2}", performance.throughput())),
+            (
+                "Correctness",
+                format!("{:.0}%", performance.correctness() * 100.0),
+            ),
+        ];
+        let column = || div().flex().flex_col();
+        let labels = column().children(lines.iter().map(|(label, _)| div().child(*label)));
+        let values = column()
+            .items_end()
+            .children(lines.into_iter().map(|(_, value)| div().child(value)));
+
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .flex()
+            .justify_center()
+            .items_center()
+            .occlude()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| {
+                    view.performance = None;
+                    cx.notify();
+                }),
+            )
+            .child(bevel_raised(
+                theme(cx),
+                div()
+                    .flex()
+                    .gap(px(8.0))
+                    .p(px(4.0))
+                    .text_size(px(11.0))
+                    .text_color(theme(cx).text)
+                    .child(labels)
+                    .child(values),
+            ))
+    }
+}
@@ -1,39 +1,171 @@
-use rand::rng;
 use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng, rng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::clock::{Clock, Timer};
+use crate::probability::mine_probabilities;
+use crate::replay::{Action, Event, Recorder};
+use crate::solver::{Board, Solver, Visible};
+use crate::topology::{Grid, Neighborhood, Topology};
+use std::time::{Duration, Instant};
+
+// Largest board edge accepted for custom games
+pub const MAX_DIMENSION: u32 = 100;
+
+// Most mines one cell can hold in the multi-mine variant
+pub const MAX_CELL_MINES: u8 = 3;
+
+// How long no-guess generation may keep rerolling before settling for a layout
+pub const DEFAULT_GENERATION_TIME_LIMIT: Duration = Duration::from_secs(2);
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Difficulty {
     Beginner,
     Intermediate,
     Expert,
+    Custom { rows: u32, cols: u32, mines: u32 },
 }
 
 impl Difficulty {
+    /// Builds a custom difficulty, rejecting boards that cannot be played.
+    pub fn custom(rows: u32, cols: u32, mines: u32) -> Result<Self, ConfigError> {
+        let difficulty = Difficulty::Custom { rows, cols, mines };
+        difficulty.validate()?;
+        Ok(difficulty)
+    }
+
+    /// The preset matching a board size, or a custom difficulty otherwise.
+    pub fn from_config(rows: u32, cols: u32, mines: u32) -> Self {
+        [
+            Difficulty::Beginner,
+            Difficulty::Intermediate,
+            Difficulty::Expert,
+        ]
+        .into_iter()
+        .find(|preset| preset.config() == (rows, cols, mines))
+        .unwrap_or(Difficulty::Custom { rows, cols, mines })
+    }
+
     pub fn config(&self) -> (u32, u32, u32) {
         match self {
             Difficulty::Beginner => (9, 9, 10),
             Difficulty::Intermediate => (16, 16, 40),
             Difficulty::Expert => (16, 30, 99),
+            Difficulty::Custom { rows, cols, mines } => (*rows, *cols, *mines),
         }
     }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let (rows, cols, mines) = self.config();
+        if rows == 0 || cols == 0 {
+            return Err(ConfigError::EmptyBoard);
+        }
+        if rows > MAX_DIMENSION || cols > MAX_DIMENSION {
+            return Err(ConfigError::TooLarge);
+        }
+        if mines == 0 {
+            return Err(ConfigError::NoMines);
+        }
+        // The first click is always safe, so at least one cell must stay free
+        if mines >= rows * cols {
+            return Err(ConfigError::TooManyMines {
+                max: rows * cols - 1,
+            });
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigError {
+    EmptyBoard,
+    TooLarge,
+    NoMines,
+    TooManyMines { max: u32 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::EmptyBoard => write!(f, "Board needs at least one row and column"),
+            ConfigError::TooLarge => {
+                write!(f, "Rows and columns are limited to {}", MAX_DIMENSION)
+            }
+            ConfigError::NoMines => write!(f, "Board needs at least one mine"),
+            ConfigError::TooManyMines { max } => write!(f, "At most {} mines fit", max),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// Why a move was refused. Refused moves change nothing and are not recorded.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MoveError {
+    OutOfBounds { row: u32, col: u32 },
+    Finished, // The game was already won or lost
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::OutOfBounds { row, col } => {
+                write!(f, "Cell ({}, {}) is not on the board", row, col)
+            }
+            MoveError::Finished => write!(f, "The game is already over"),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum CellContent {
     Empty,
-    Mine,
+    Mine(u8), // How many; always 1 outside the multi-mine variant
     Number(u8),
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+impl CellContent {
+    pub fn is_mine(self) -> bool {
+        matches!(self, CellContent::Mine(_))
+    }
+
+    /// Mines in the cell, 0 if it is safe.
+    pub fn mines(self) -> u8 {
+        match self {
+            CellContent::Mine(count) => count,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub enum CellState {
     Hidden,
     Revealed,
-    Flagged,
+    Flagged(u8), // How many flags; more than 1 only in the multi-mine variant
     QuestionMark,
 }
 
-#[derive(Clone, Debug)]
+impl CellState {
+    pub fn is_flagged(self) -> bool {
+        matches!(self, CellState::Flagged(_))
+    }
+
+    /// Flags on the cell, 0 if it has none.
+    pub fn flags(self) -> u8 {
+        match self {
+            CellState::Flagged(count) => count,
+            _ => 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Cell {
     pub content: CellContent,
     pub state: CellState,
@@ -50,9 +182,121 @@ impl Cell {
             wrong_flag: false,
         }
     }
+
+    // Content is only seen once revealed, so state and markers are enough
+    fn looks_different(&self, other: &Cell) -> bool {
+        self.state != other.state
+            || self.exploded != other.exploded
+            || self.wrong_flag != other.wrong_flag
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Hint {
+    // Provably safe from what the player can see
+    Safe {
+        row: u32,
+        col: u32,
+    },
+    // Nothing is provable; the least likely mine
+    Guess {
+        row: u32,
+        col: u32,
+        mine_probability: f64,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct Clicks {
+    pub useful: u32, // Changed the board
+    pub wasted: u32,
 }
 
-#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+impl Clicks {
+    pub fn total(&self) -> u32 {
+        self.useful + self.wasted
+    }
+}
+
+/// Every click sent to the game while it was in progress, by kind.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub struct ClickStats {
+    pub left: Clicks,
+    pub right: Clicks,
+    pub chords: Clicks,
+}
+
+impl ClickStats {
+    pub fn total(&self) -> u32 {
+        self.left.total() + self.right.total() + self.chords.total()
+    }
+
+    pub fn useful(&self) -> u32 {
+        self.left.useful + self.right.useful + self.chords.useful
+    }
+
+    pub fn wasted(&self) -> u32 {
+        self.total() - self.useful()
+    }
+
+    fn count(&mut self, action: Action, useful: bool) {
+        let clicks = match action {
+            Action::Reveal { .. } => &mut self.left,
+            Action::Flag { .. } => &mut self.right,
+            Action::Chord { .. } => &mut self.chords,
+            Action::Undo | Action::Redo => return,
+        };
+        if useful {
+            clicks.useful += 1;
+        } else {
+            clicks.wasted += 1;
+        }
+    }
+}
+
+/// What a move did. An outcome with no changed cells was a wasted click.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct Outcome {
+    /// Cells that look different afterwards, row by row. Mines laid by the
+    /// first click stay hidden, so they aren't listed.
+    pub changed: Vec<(u32, u32)>,
+    /// Status before and after, if the move started, won or lost the game.
+    pub transition: Option<(GameStatus, GameStatus)>,
+}
+
+impl Outcome {
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.transition.is_none()
+    }
+
+    pub fn started(&self) -> bool {
+        matches!(self.transition, Some((GameStatus::NotStarted, _)))
+    }
+
+    pub fn won(&self) -> bool {
+        matches!(self.transition, Some((_, GameStatus::Won)))
+    }
+
+    pub fn lost(&self) -> bool {
+        matches!(self.transition, Some((_, GameStatus::Lost)))
+    }
+}
+
+// One reversible move: every cell it changed plus the counters around them
+#[derive(Clone, Debug)]
+struct Step {
+    cells: Vec<(usize, Cell, Cell)>, // index, before, after
+    status: (GameStatus, GameStatus),
+    flags_placed: (u32, u32),
+}
+
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum GameStatus {
     NotStarted,
     Playing,
@@ -60,119 +304,531 @@ pub enum GameStatus {
     Lost,
 }
 
+// Saved games keep everything but the undo history
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Minesweeper {
     pub rows: u32,
     pub cols: u32,
     pub mines: u32,
+    #[serde(default)]
+    pub grid: Grid, // Set before the first click, like no_guess
+    #[serde(default)]
+    pub wrap: bool, // Opposite edges touch
+    #[serde(default)]
+    pub rule: Neighborhood,
+    // Cells may hold up to MAX_CELL_MINES mines; `mines` counts mines, not cells
+    #[serde(default)]
+    pub multi_mine: bool,
     pub cells: Vec<Cell>,
     pub status: GameStatus,
     pub flags_placed: u32,
-    pub start_time: Option<std::time::Instant>,
-    pub elapsed_seconds: u32,
+    // Runs while playing and not paused
+    #[serde(rename = "elapsed_ms")]
+    timer: Timer,
+    pub seed: u64,
+    // Only accept layouts that can be cleared from the first click without guessing
+    pub no_guess: bool,
+    pub generation_time_limit: Duration,
+    // Set once mines are placed: false if no-guess generation ran out of time
+    pub guess_free: bool,
+    pub hints_used: u32,
+    pub undos_used: u32,
+    // The probability heatmap was on at some point during play
+    #[serde(default)]
+    pub heatmap_used: bool,
+    // Pasted or opened from a file: how it was played can't be known
+    #[serde(default)]
+    pub imported: bool,
+    #[serde(default)]
+    pub clicks: ClickStats,
+    #[serde(skip)]
+    undo_stack: Vec<Step>,
+    #[serde(skip)]
+    redo_stack: Vec<Step>,
+    recorder: Recorder,
 }
 
 impl Minesweeper {
     pub fn new(difficulty: Difficulty) -> Self {
+        Self::with_seed(difficulty, rng().random())
+    }
+
+    /// Creates a game whose mine layout is fully determined by `seed`, the
+    /// difficulty and the first revealed cell.
+    ///
+    /// The layout is drawn from `ChaCha8Rng::seed_from_u64(seed)`: all cells in
+    /// row-major order, minus the first click (removed with `swap_remove`), are
+    /// shuffled with rand's Fisher-Yates `shuffle` and the first `mines` cells
+    /// become mines. Shared seeds rely on this, so changing any step breaks them.
+    ///
+    /// In no-guess mode the same stream keeps being shuffled until a layout
+    /// passes, so a seed only reproduces if generation finished within its
+    /// time limit.
+    pub fn with_seed(difficulty: Difficulty, seed: u64) -> Self {
         let (rows, cols, mines) = difficulty.config();
         Self {
             rows,
             cols,
             mines,
+            grid: Grid::Square,
+            wrap: false,
+            rule: Neighborhood::Classic,
+            multi_mine: false,
             cells: vec![Cell::new(); (rows * cols) as usize],
             status: GameStatus::NotStarted,
             flags_placed: 0,
-            start_time: None,
-            elapsed_seconds: 0,
+            timer: Timer::default(),
+            seed,
+            no_guess: false,
+            generation_time_limit: DEFAULT_GENERATION_TIME_LIMIT,
+            guess_free: false,
+            hints_used: 0,
+            undos_used: 0,
+            heatmap_used: false,
+            imported: false,
+            clicks: ClickStats::default(),
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            recorder: Recorder::default(),
+        }
+    }
+
+    /// Puts mines on the given cell indices instead of a generated layout;
+    /// the first click is then not guaranteed to be safe. An index listed
+    /// twice holds two mines. Used to play back replays, once the grid is set.
+    pub fn set_layout(&mut self, mines: &[usize]) {
+        for cell in &mut self.cells {
+            cell.content = CellContent::Empty;
+        }
+        for &idx in mines {
+            let count = self.cells[idx].content.mines();
+            self.cells[idx].content = CellContent::Mine(count + 1);
+        }
+        self.count_numbers();
+    }
+
+    /// Indices of every mine, a cell with several listed once per mine, or
+    /// nothing before the first click placed them.
+    pub fn mine_layout(&self) -> Vec<usize> {
+        self.cells
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, cell)| std::iter::repeat_n(idx, cell.content.mines() as usize))
+            .collect()
+    }
+
+    /// Times the game with `clock` from now on instead of the system clock.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.timer.set_clock(clock);
+    }
+
+    /// Playing time so far. The clock starts with the first reveal, stops
+    /// when the game is won or lost, and doesn't count time spent paused.
+    pub fn elapsed(&self) -> Duration {
+        self.timer.elapsed()
+    }
+
+    pub fn elapsed_ms(&self) -> u64 {
+        self.elapsed().as_millis() as u64
+    }
+
+    /// Whole seconds played, as the classic timer shows them.
+    pub fn elapsed_seconds(&self) -> u32 {
+        self.elapsed().as_secs() as u32
+    }
+
+    /// Sets the clock reading and stops the clock; replays drive their
+    /// game's timer this way.
+    pub fn set_elapsed(&mut self, elapsed: Duration) {
+        self.timer.set(elapsed);
+    }
+
+    /// A game in progress whose clock is stopped.
+    pub fn is_paused(&self) -> bool {
+        self.status == GameStatus::Playing && !self.timer.is_running()
+    }
+
+    /// Stops the clock until `resume` or the next move.
+    pub fn pause(&mut self) {
+        self.timer.stop();
+    }
+
+    /// Restarts a paused clock. Loaded games start paused.
+    pub fn resume(&mut self) {
+        if self.status == GameStatus::Playing {
+            self.timer.start();
+        }
+    }
+
+    // Runs the clock while playing, from `now` if it was stopped, and stops
+    // it otherwise. Time banked before an undo back to the start is kept, so
+    // recorded times never go backwards.
+    fn sync_clock(&mut self, now: Instant) {
+        if self.status == GameStatus::Playing {
+            self.timer.start_at(now);
+        } else {
+            self.timer.stop_at(now);
+        }
+    }
+
+    /// Every move made so far, including undo and redo, in the order played.
+    pub fn recording(&self) -> &[Event] {
+        self.recorder.events()
+    }
+
+    /// Whether `recording` covers the game from an untouched board; not so
+    /// for a board pasted part-way through.
+    pub fn recorded_from_start(&self) -> bool {
+        !self.recorder.is_midway()
+    }
+
+    // For boards set up some way other than by playing
+    pub(crate) fn start_recording_midway(&mut self) {
+        self.recorder.start_midway();
+    }
+
+    /// Flags standing on the board, counted the way `flags_placed` is.
+    pub(crate) fn flags_on_board(&self) -> u32 {
+        self.cells
+            .iter()
+            .map(|cell| cell.state.flags() as u32)
+            .sum()
+    }
+
+    // Whether `flags_placed` agrees with the board, for games read from
+    // outside. Only games still open are checked: a loss reveals wrong flags,
+    // and no flag moves after the end anyway.
+    pub(crate) fn flag_count_matches(&self) -> bool {
+        matches!(self.status, GameStatus::Won | GameStatus::Lost)
+            || self.flags_placed == self.flags_on_board()
+    }
+
+    /// Games that had help (hints, undo or the heatmap) don't count toward
+    /// records or statistics.
+    pub fn is_assisted(&self) -> bool {
+        self.hints_used > 0 || self.undos_used > 0 || self.heatmap_used
+    }
+
+    /// Hex, wrap-around, multi-mine and other rule changes; like assisted
+    /// games, these are kept out of records.
+    pub fn is_variant(&self) -> bool {
+        self.grid != Grid::Square
+            || self.wrap
+            || self.rule != Neighborhood::Classic
+            || self.multi_mine
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Takes back the last move, including a losing one. Returns false if
+    /// there is nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.undo_stack.pop() else {
+            return false;
+        };
+        for (idx, before, _) in &step.cells {
+            self.cells[*idx] = before.clone();
         }
+        self.status = step.status.0;
+        self.flags_placed = step.flags_placed.0;
+        self.sync_clock(self.timer.now());
+        self.undos_used += 1;
+        self.redo_stack.push(step);
+        self.recorder.push(Action::Undo, self.elapsed_ms());
+        true
     }
 
-    pub fn reset(&mut self, difficulty: Difficulty) {
-        *self = Self::new(difficulty);
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.redo_stack.pop() else {
+            return false;
+        };
+        for (idx, _, after) in &step.cells {
+            self.cells[*idx] = after.clone();
+        }
+        self.status = step.status.1;
+        self.flags_placed = step.flags_placed.1;
+        self.sync_clock(self.timer.now());
+        self.undo_stack.push(step);
+        self.recorder.push(Action::Redo, self.elapsed_ms());
+        true
     }
 
+    // Runs a move on a cell, counting the click and keeping it as a replay
+    // event, and keeps what it changed as an undo step. Moves that change
+    // nothing are wasted clicks: no undo step, and the redo history is left
+    // alone. Moves off the board or after the end are refused outright.
+    fn record(
+        &mut self,
+        action: Action,
+        (row, col): (u32, u32),
+        apply: impl FnOnce(&mut Self),
+    ) -> Result<Outcome, MoveError> {
+        if !self.contains(row, col) {
+            return Err(MoveError::OutOfBounds { row, col });
+        }
+        if matches!(self.status, GameStatus::Won | GameStatus::Lost) {
+            return Err(MoveError::Finished);
+        }
+        // Timed from the click, not after laying mines, which can take a while
+        let now = self.timer.now();
+        let cells = self.cells.clone();
+        let (status, flags_placed) = (self.status, self.flags_placed);
+
+        apply(self);
+        self.sync_clock(now);
+
+        let changed: Vec<(usize, Cell, Cell)> = cells
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, before)| *before != self.cells[*idx])
+            .map(|(idx, before)| (idx, before, self.cells[idx].clone()))
+            .collect();
+        let outcome = Outcome {
+            // Laying mines changes hidden cells too, but only what shows is reported
+            changed: changed
+                .iter()
+                .filter(|(_, before, after)| before.looks_different(after))
+                .map(|(idx, _, _)| (*idx as u32 / self.cols, *idx as u32 % self.cols))
+                .collect(),
+            transition: (status != self.status).then_some((status, self.status)),
+        };
+        let useful = !outcome.is_empty() || flags_placed != self.flags_placed;
+        self.clicks.count(action, useful);
+        self.recorder
+            .push(action, self.timer.elapsed_at(now).as_millis() as u64);
+        if !useful {
+            return Ok(outcome);
+        }
+        self.undo_stack.push(Step {
+            cells: changed,
+            status: (status, self.status),
+            flags_placed: (flags_placed, self.flags_placed),
+        });
+        self.redo_stack.clear();
+        Ok(outcome)
+    }
+
+    /// Suggests a provably safe cell, or the lowest-risk guess when no cell is
+    /// provable. Only visible state is used. Each hint given counts in `hints_used`.
+    /// The solver knows one mine per cell, so multi-mine games get no hints.
+    /// Nor does a board with nothing revealed: any first click is safe.
+    pub fn hint(&mut self) -> Option<Hint> {
+        let revealed = self
+            .cells
+            .iter()
+            .any(|cell| cell.state == CellState::Revealed);
+        if self.multi_mine || self.status != GameStatus::Playing || !revealed {
+            return None;
+        }
+        let board = Board::from_game(self);
+        let hint = if let Some((row, col)) = Solver::default().find_safe(&board) {
+            Hint::Safe { row, col }
+        } else {
+            let ((row, col), mine_probability) = mine_probabilities(&board).safest()?;
+            Hint::Guess {
+                row,
+                col,
+                mine_probability,
+            }
+        };
+        self.hints_used += 1;
+        Some(hint)
+    }
+
+    pub fn topology(&self) -> &'static dyn Topology {
+        self.grid.topology(self.rule)
+    }
+
+    pub fn contains(&self, row: u32, col: u32) -> bool {
+        row < self.rows && col < self.cols
+    }
+
+    /// Row-major index of a cell; panics off the board, see `contains`.
     pub fn index(&self, row: u32, col: u32) -> usize {
-        (row * self.cols + col) as usize
+        self.topology().index(self.cols, row, col)
     }
 
     // Made public for the view to use in rendering chording
     pub fn neighbors(&self, row: u32, col: u32) -> Vec<(u32, u32)> {
-        let mut neighbors = Vec::with_capacity(8);
-        for dr in -1..=1 {
-            for dc in -1..=1 {
-                if dr == 0 && dc == 0 {
-                    continue;
-                }
-                let nr = row as i32 + dr;
-                let nc = col as i32 + dc;
-                if nr >= 0 && nr < self.rows as i32 && nc >= 0 && nc < self.cols as i32 {
-                    neighbors.push((nr as u32, nc as u32));
-                }
-            }
+        self.topology()
+            .neighbors(self.rows, self.cols, row, col, self.wrap)
+    }
+
+    /// Whether the first click will search for a no-guess layout, which can
+    /// take up to `generation_time_limit`.
+    pub fn needs_layout_search(&self) -> bool {
+        self.no_guess
+            && !self.multi_mine
+            && self.status == GameStatus::NotStarted
+            && self.mine_layout().is_empty()
+    }
+
+    /// Lays the mines for a first click on (`row`, `col`) without making it,
+    /// so a slow search can run away from the UI; `reveal` then keeps them.
+    pub fn lay_mines_for(&mut self, row: u32, col: u32) {
+        if self.contains(row, col) && self.mine_layout().is_empty() {
+            self.place_mines(row, col);
         }
-        neighbors
     }
 
     fn place_mines(&mut self, safe_row: u32, safe_col: u32) {
-        let total_cells = self.rows * self.cols;
         let safe_index = self.index(safe_row, safe_col);
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
 
-        let mut indices: Vec<usize> = (0..total_cells as usize).collect();
-        // Remove safe index from possible mine locations to ensure first click is safe
-        if let Some(pos) = indices.iter().position(|&x| x == safe_index) {
-            indices.swap_remove(pos);
+        // The solver can't vouch for multi-mine layouts
+        if !self.no_guess || self.multi_mine {
+            self.lay_mines(&mut rng, &[safe_index]);
+            self.guess_free = false;
+            return;
         }
 
-        let mut rng = rng();
-        indices.shuffle(&mut rng);
+        // Keep the whole first-click neighbourhood free so the game starts with an opening
+        let mut safe = vec![safe_index];
+        let neighborhood: Vec<usize> = self
+            .neighbors(safe_row, safe_col)
+            .iter()
+            .map(|&(r, c)| self.index(r, c))
+            .collect();
+        if self.cells.len() - neighborhood.len() > self.mines as usize {
+            safe.extend(neighborhood);
+        }
 
-        let mine_indices = &indices[0..self.mines as usize];
-        for &idx in mine_indices {
-            self.cells[idx].content = CellContent::Mine;
+        let deadline = Instant::now() + self.generation_time_limit;
+        loop {
+            self.lay_mines(&mut rng, &safe);
+            if self.solvable_from(safe_row, safe_col) {
+                self.guess_free = true;
+                return;
+            }
+            if Instant::now() >= deadline {
+                // Settle for the last layout rather than freezing the UI
+                self.guess_free = false;
+                return;
+            }
+        }
+    }
+
+    fn lay_mines(&mut self, rng: &mut ChaCha8Rng, safe: &[usize]) {
+        let mut indices: Vec<usize> = (0..self.cells.len()).collect();
+        // Remove safe indices from possible mine locations to ensure first click is safe
+        for safe_index in safe {
+            if let Some(pos) = indices.iter().position(|x| x == safe_index) {
+                indices.swap_remove(pos);
+            }
+        }
+        if self.multi_mine {
+            // One slot per mine a cell can hold, so cells fill up to the limit
+            let slots = indices.len() * MAX_CELL_MINES as usize;
+            indices = indices.into_iter().cycle().take(slots).collect();
         }
 
-        // Calculate numbers
+        indices.shuffle(rng);
+
+        // Never slice past the free cells, even if an unvalidated config got through
+        let mine_count = (self.mines as usize).min(indices.len());
+        self.set_layout(&indices[0..mine_count]);
+    }
+
+    // Numbers count mines, not mined cells
+    fn count_numbers(&mut self) {
         for r in 0..self.rows {
             for c in 0..self.cols {
                 let idx = self.index(r, c);
-                if self.cells[idx].content == CellContent::Mine {
+                if self.cells[idx].content.is_mine() {
                     continue;
                 }
-                let count = self
+                let count: u32 = self
                     .neighbors(r, c)
                     .iter()
-                    .filter(|&&(nr, nc)| {
-                        self.cells[self.index(nr, nc)].content == CellContent::Mine
-                    })
-                    .count();
-                if count > 0 {
-                    self.cells[idx].content = CellContent::Number(count as u8);
+                    .map(|&(nr, nc)| self.cells[self.index(nr, nc)].content.mines() as u32)
+                    .sum();
+                self.cells[idx].content = if count > 0 {
+                    CellContent::Number(count.min(u8::MAX as u32) as u8)
+                } else {
+                    CellContent::Empty
+                };
+            }
+        }
+    }
+
+    // Plays the board from the first click, opening only what the solver can
+    // prove from visible numbers. True if every safe cell ends up revealed.
+    fn solvable_from(&self, row: u32, col: u32) -> bool {
+        let solver = Solver::default();
+        let mut board = Board::unknown(self);
+        let safe_total = self.cells.iter().filter(|c| !c.content.is_mine()).count();
+        let mut opened = 0;
+        let mut to_open = vec![self.index(row, col)];
+
+        loop {
+            while let Some(idx) = to_open.pop() {
+                if board.cells[idx] != Visible::Unknown {
+                    continue;
                 }
+                let count = match self.cells[idx].content {
+                    // A deduction can never point at a mine
+                    CellContent::Mine(_) => return false,
+                    CellContent::Empty => {
+                        to_open.extend(&board.neighbors[idx]);
+                        0
+                    }
+                    CellContent::Number(n) => n,
+                };
+                board.cells[idx] = Visible::Clear(count);
+                opened += 1;
+            }
+            if opened == safe_total {
+                return true;
+            }
+
+            let deductions = solver.deduce(&board);
+            if deductions.is_empty() {
+                return false;
+            }
+            to_open.extend(deductions.safe.iter().map(|&(r, c)| self.index(r, c)));
+            for (r, c) in deductions.mines {
+                let idx = self.index(r, c);
+                board.cells[idx] = Visible::Mine;
             }
         }
     }
 
-    pub fn reveal(&mut self, row: u32, col: u32) {
+    pub fn reveal(&mut self, row: u32, col: u32) -> Result<Outcome, MoveError> {
+        self.record(Action::Reveal { row, col }, (row, col), |game| {
+            game.reveal_cell(row, col)
+        })
+    }
+
+    fn reveal_cell(&mut self, row: u32, col: u32) {
         if self.status == GameStatus::Won || self.status == GameStatus::Lost {
             return;
         }
 
         if self.status == GameStatus::NotStarted {
             self.status = GameStatus::Playing;
-            self.start_time = Some(std::time::Instant::now());
-            self.place_mines(row, col);
+            // Games built from a layout already have their mines
+            if self.mine_layout().is_empty() {
+                self.place_mines(row, col);
+            }
         }
 
         let idx = self.index(row, col);
         let cell = &mut self.cells[idx];
 
-        if cell.state == CellState::Flagged || cell.state == CellState::Revealed {
+        if cell.state.is_flagged() || cell.state == CellState::Revealed {
             return;
         }
 
         cell.state = CellState::Revealed;
 
         match cell.content {
-            CellContent::Mine => {
+            CellContent::Mine(_) => {
                 self.status = GameStatus::Lost;
                 cell.exploded = true;
                 self.reveal_all_mines();
@@ -199,7 +855,13 @@ impl Minesweeper {
         }
     }
 
-    pub fn toggle_flag(&mut self, row: u32, col: u32) {
+    pub fn toggle_flag(&mut self, row: u32, col: u32) -> Result<Outcome, MoveError> {
+        self.record(Action::Flag { row, col }, (row, col), |game| {
+            game.cycle_mark(row, col)
+        })
+    }
+
+    fn cycle_mark(&mut self, row: u32, col: u32) {
         if self.status != GameStatus::Playing && self.status != GameStatus::NotStarted {
             return;
         }
@@ -207,12 +869,17 @@ impl Minesweeper {
         let cell = &mut self.cells[idx];
         match cell.state {
             CellState::Hidden => {
-                cell.state = CellState::Flagged;
+                cell.state = CellState::Flagged(1);
                 self.flags_placed += 1;
             }
-            CellState::Flagged => {
+            // Multi-mine cells take one more flag per click, up to the limit
+            CellState::Flagged(count) if self.multi_mine && count < MAX_CELL_MINES => {
+                cell.state = CellState::Flagged(count + 1);
+                self.flags_placed += 1;
+            }
+            CellState::Flagged(count) => {
                 cell.state = CellState::QuestionMark;
-                self.flags_placed -= 1;
+                self.flags_placed -= count as u32;
             }
             CellState::QuestionMark => {
                 cell.state = CellState::Hidden;
@@ -221,47 +888,49 @@ impl Minesweeper {
         }
     }
 
-    pub fn chord(&mut self, row: u32, col: u32) -> bool {
+    /// Opens the neighbours of a number whose flags are all placed; with the
+    /// wrong number of flags nothing changes.
+    pub fn chord(&mut self, row: u32, col: u32) -> Result<Outcome, MoveError> {
+        self.record(Action::Chord { row, col }, (row, col), |game| {
+            game.chord_cells(row, col)
+        })
+    }
+
+    fn chord_cells(&mut self, row: u32, col: u32) {
         if self.status != GameStatus::Playing {
-            return false;
+            return;
         }
         let idx = self.index(row, col);
         if self.cells[idx].state != CellState::Revealed {
-            return false;
+            return;
         }
 
         if let CellContent::Number(n) = self.cells[idx].content {
             let neighbors = self.neighbors(row, col);
-            let flag_count = neighbors
+            let flag_count: u32 = neighbors
                 .iter()
-                .filter(|&&(nr, nc)| self.cells[self.index(nr, nc)].state == CellState::Flagged)
-                .count();
+                .map(|&(nr, nc)| self.cells[self.index(nr, nc)].state.flags() as u32)
+                .sum();
 
-            if flag_count == n as usize {
+            if flag_count == n as u32 {
                 for (nr, nc) in neighbors {
                     if self.cells[self.index(nr, nc)].state == CellState::Hidden
                         || self.cells[self.index(nr, nc)].state == CellState::QuestionMark
                     {
-                        self.reveal(nr, nc);
+                        self.reveal_cell(nr, nc);
                     }
                 }
-                return true;
             }
         }
-        false
     }
 
     fn reveal_all_mines(&mut self) {
         for i in 0..self.cells.len() {
-            if self.cells[i].content == CellContent::Mine
-                && self.cells[i].state != CellState::Flagged
-            {
+            if self.cells[i].content.is_mine() && !self.cells[i].state.is_flagged() {
                 self.cells[i].state = CellState::Revealed;
             }
             // Check for wrong flags
-            if self.cells[i].content != CellContent::Mine
-                && self.cells[i].state == CellState::Flagged
-            {
+            if !self.cells[i].content.is_mine() && self.cells[i].state.is_flagged() {
                 self.cells[i].wrong_flag = true;
                 self.cells[i].state = CellState::Revealed; // Show it was wrong
             }
@@ -269,27 +938,268 @@ impl Minesweeper {
     }
 
     fn check_win(&mut self) {
-        let total_cells = self.rows * self.cols;
-        let revealed_count = self
+        let cleared = self
             .cells
             .iter()
-            .filter(|c| c.state == CellState::Revealed)
-            .count();
-        if revealed_count as u32 == total_cells - self.mines {
+            .all(|c| c.content.is_mine() || c.state == CellState::Revealed);
+        if cleared {
             self.status = GameStatus::Won;
             self.flag_all_mines();
         }
     }
 
+    // As in WinXP, a win flags every mine and the counter reaches zero
     fn flag_all_mines(&mut self) {
-        self.flags_placed = 0;
         for cell in &mut self.cells {
-            if cell.content == CellContent::Mine {
-                cell.state = CellState::Flagged;
-                self.flags_placed += 1;
+            if let CellContent::Mine(count) = cell.content {
+                cell.state = CellState::Flagged(count);
             }
         }
-        // Actually, in WinXP, the flag count matches mines when won.
         self.flags_placed = self.mines;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // M 1 1 M: the two middle cells win it
+    fn small_game() -> Minesweeper {
+        let mut game = Minesweeper::new(Difficulty::custom(1, 4, 2).unwrap());
+        game.set_layout(&[0, 3]);
+        game
+    }
+
+    fn state(game: &Minesweeper, col: u32) -> CellState {
+        game.cells[game.index(0, col)].state
+    }
+
+    #[test]
+    fn undoes_and_redoes_several_moves_in_a_row() {
+        let mut game = small_game();
+        game.toggle_flag(0, 0).unwrap();
+        game.reveal(0, 1).unwrap();
+        game.reveal(0, 2).unwrap();
+        assert_eq!(game.status, GameStatus::Won);
+
+        assert!(game.undo());
+        assert_eq!(game.status, GameStatus::Playing);
+        assert_eq!(state(&game, 2), CellState::Hidden);
+        assert_eq!(game.flags_placed, 1);
+        assert!(game.undo());
+        assert_eq!(game.status, GameStatus::NotStarted);
+        assert_eq!(state(&game, 1), CellState::Hidden);
+        assert!(game.undo());
+        assert_eq!(state(&game, 0), CellState::Hidden);
+        assert_eq!(game.flags_placed, 0);
+        assert!(!game.undo());
+        assert!(!game.can_undo());
+
+        assert!(game.redo());
+        assert_eq!(game.flags_placed, 1);
+        assert!(game.redo());
+        assert!(game.redo());
+        assert_eq!(game.status, GameStatus::Won);
+        assert_eq!(game.flags_placed, 2);
+        assert!(!game.redo());
+        assert_eq!(game.undos_used, 3);
+    }
+
+    #[test]
+    fn a_new_move_clears_what_could_be_redone() {
+        let mut game = small_game();
+        game.reveal(0, 1).unwrap();
+        game.toggle_flag(0, 0).unwrap();
+        assert!(game.undo());
+        assert!(game.can_redo());
+        game.toggle_flag(0, 3).unwrap();
+        assert!(!game.can_redo());
+        assert!(!game.redo());
+        assert_eq!(state(&game, 0), CellState::Hidden);
+        assert_eq!(state(&game, 3), CellState::Flagged(1));
+    }
+
+    #[test]
+    fn a_wasted_click_leaves_redo_alone() {
+        let mut game = small_game();
+        game.reveal(0, 1).unwrap();
+        game.toggle_flag(0, 0).unwrap();
+        assert!(game.undo());
+        // Flagging a revealed cell changes nothing
+        game.toggle_flag(0, 1).unwrap();
+        assert!(game.can_redo());
+    }
+
+    #[test]
+    fn undoes_a_loss() {
+        let mut game = small_game();
+        game.reveal(0, 1).unwrap();
+        game.toggle_flag(0, 2).unwrap();
+        game.reveal(0, 0).unwrap();
+        assert_eq!(game.status, GameStatus::Lost);
+        assert!(game.cells[0].exploded);
+        assert!(game.cells[2].wrong_flag);
+
+        assert!(game.undo());
+        assert_eq!(game.status, GameStatus::Playing);
+        assert_eq!(state(&game, 0), CellState::Hidden);
+        assert!(!game.cells[0].exploded);
+        assert_eq!(state(&game, 2), CellState::Flagged(1));
+        assert!(!game.cells[2].wrong_flag);
+        assert_eq!(state(&game, 3), CellState::Hidden);
+        assert_eq!(game.flags_placed, 1);
+
+        assert!(game.redo());
+        assert_eq!(game.status, GameStatus::Lost);
+    }
+
+    #[test]
+    fn moves_off_the_board_or_after_the_end_are_refused() {
+        let mut game = small_game();
+        let off = Err(MoveError::OutOfBounds { row: 1, col: 0 });
+        assert_eq!(game.reveal(1, 0), off);
+        assert_eq!(game.toggle_flag(1, 0), off);
+        assert_eq!(game.chord(1, 0), off);
+        assert_eq!(game.clicks.total(), 0);
+
+        game.reveal(0, 0).unwrap();
+        assert_eq!(game.reveal(0, 1), Err(MoveError::Finished));
+        assert_eq!(game.toggle_flag(0, 1), Err(MoveError::Finished));
+        assert_eq!(game.chord(0, 1), Err(MoveError::Finished));
+    }
+
+    #[test]
+    fn reveal_lists_the_cells_it_shows() {
+        let mut game = small_game();
+        let outcome = game.reveal(0, 1).unwrap();
+        assert_eq!(outcome.changed, vec![(0, 1)]);
+        assert!(outcome.started());
+
+        // An opening floods to the end, and the win flags the mine
+        let mut game = Minesweeper::new(Difficulty::custom(1, 5, 1).unwrap());
+        game.set_layout(&[0]);
+        let outcome = game.reveal(0, 4).unwrap();
+        assert_eq!(
+            outcome.changed,
+            vec![(0, 0), (0, 1), (0, 2), (0, 3), (0, 4)]
+        );
+        assert_eq!(
+            outcome.transition,
+            Some((GameStatus::NotStarted, GameStatus::Won))
+        );
+    }
+
+    #[test]
+    fn first_reveal_leaves_the_mines_it_lays_out_of_the_list() {
+        let mut game = Minesweeper::with_seed(Difficulty::Beginner, 42);
+        let outcome = game.reveal(4, 4).unwrap();
+        assert!(!game.mine_layout().is_empty());
+        for (row, col) in outcome.changed {
+            assert_eq!(game.cells[game.index(row, col)].state, CellState::Revealed);
+        }
+    }
+
+    #[test]
+    fn flag_lists_its_cell_or_nothing() {
+        let mut game = small_game();
+        let outcome = game.toggle_flag(0, 0).unwrap();
+        assert_eq!(outcome.changed, vec![(0, 0)]);
+        assert_eq!(outcome.transition, None);
+
+        game.reveal(0, 1).unwrap();
+        assert!(game.toggle_flag(0, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn chord_lists_what_it_opens() {
+        let mut game = small_game();
+        game.reveal(0, 1).unwrap();
+        // No flag beside the 1 yet, so nothing opens
+        assert!(game.chord(0, 1).unwrap().is_empty());
+
+        game.toggle_flag(0, 0).unwrap();
+        let outcome = game.chord(0, 1).unwrap();
+        assert_eq!(outcome.changed, vec![(0, 2), (0, 3)]);
+        assert!(outcome.won());
+    }
+
+    fn first_click_layout(difficulty: Difficulty, seed: u64, row: u32, col: u32) -> Vec<usize> {
+        let mut game = Minesweeper::with_seed(difficulty, seed);
+        game.reveal(row, col).unwrap();
+        game.mine_layout()
+    }
+
+    #[test]
+    fn a_seed_always_gives_the_same_layout() {
+        // Pinned, as shared seeds must keep giving this board
+        assert_eq!(
+            first_click_layout(Difficulty::Beginner, 42, 0, 0),
+            vec![27, 28, 36, 51, 55, 59, 64, 73, 75, 79]
+        );
+        let expert = first_click_layout(Difficulty::Expert, 7, 8, 15);
+        assert_eq!(first_click_layout(Difficulty::Expert, 7, 8, 15), expert);
+        assert_ne!(first_click_layout(Difficulty::Expert, 8, 8, 15), expert);
+        // Clicking where the layout had a mine moves it
+        let mined = expert[0] as u32;
+        let moved = first_click_layout(Difficulty::Expert, 7, mined / 30, mined % 30);
+        assert!(!moved.contains(&(mined as usize)));
+        assert_eq!(moved.len(), 99);
+    }
+
+    #[test]
+    fn no_guess_boards_can_be_cleared_by_the_solver_alone() {
+        for seed in 0..5 {
+            let mut game = Minesweeper::with_seed(Difficulty::Intermediate, seed);
+            game.no_guess = true;
+            game.generation_time_limit = Duration::from_secs(60);
+            game.reveal(8, 8).unwrap();
+            assert!(game.guess_free, "seed {}", seed);
+
+            while game.status == GameStatus::Playing {
+                let deductions = crate::solver::deduce(&game);
+                assert!(!deductions.safe.is_empty() || !deductions.mines.is_empty());
+                for (row, col) in deductions.mines {
+                    game.toggle_flag(row, col).unwrap();
+                }
+                for (row, col) in deductions.safe {
+                    if game.status == GameStatus::Playing {
+                        game.reveal(row, col).unwrap();
+                    }
+                }
+            }
+            assert_eq!(game.status, GameStatus::Won, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn mines_laid_ahead_are_kept_by_the_first_click() {
+        let mut ahead = Minesweeper::with_seed(Difficulty::Beginner, 3);
+        ahead.no_guess = true;
+        assert!(ahead.needs_layout_search());
+        ahead.lay_mines_for(4, 4);
+        assert!(!ahead.needs_layout_search());
+        let layout = ahead.mine_layout();
+        ahead.reveal(4, 4).unwrap();
+        assert_eq!(ahead.mine_layout(), layout);
+
+        let mut direct = Minesweeper::with_seed(Difficulty::Beginner, 3);
+        direct.no_guess = true;
+        direct.reveal(4, 4).unwrap();
+        assert_eq!(direct.mine_layout(), layout);
+        assert_eq!(direct.guess_free, ahead.guess_free);
+    }
+
+    #[test]
+    fn no_hint_before_anything_is_revealed() {
+        let mut game = Minesweeper::with_seed(Difficulty::Beginner, 42);
+        assert_eq!(game.hint(), None);
+        game.toggle_flag(0, 0).unwrap();
+        assert_eq!(game.hint(), None);
+        assert_eq!(game.hints_used, 0);
+
+        game.toggle_flag(0, 0).unwrap();
+        game.reveal(0, 0).unwrap();
+        assert!(game.hint().is_some());
+        assert_eq!(game.hints_used, 1);
+    }
+}
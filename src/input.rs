@@ -0,0 +1,230 @@
+use gpui::*;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use crate::{
+    CopyBoard, CursorChord, CursorDown, CursorFlag, CursorLeft, CursorReveal, CursorRight,
+    CursorUp, DiffBeginner, DiffExpert, DiffIntermediate, MinesweeperView, NewGame, OpenGame,
+    OpenReplay, PasteBoard, Pause, Redo, ReplayFaster, ReplayPlayPause, ReplaySlower,
+    ReplayStepBack, ReplayStepForward, SaveGame, ShowHint, Undo,
+};
+use minesweep::bindings::{Bindings, Button, ButtonAction, KeyAction, bindings_path};
+use minesweep::game::CellState;
+
+// How often the bindings file is checked for changes
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+// Cell actions work on the keyboard cursor, which replays don't have
+const PLAYING: &str = "Minesweeper && !Replay";
+
+// Keys that can't be changed in the bindings file
+fn fixed_key_bindings() -> Vec<KeyBinding> {
+    vec![
+        KeyBinding::new("ctrl-shift-z", Redo, Some("Minesweeper")),
+        KeyBinding::new("ctrl-c", CopyBoard, Some("Minesweeper")),
+        KeyBinding::new("ctrl-v", PasteBoard, Some("Minesweeper")),
+        KeyBinding::new("ctrl-o", OpenGame, Some("Minesweeper")),
+        KeyBinding::new("ctrl-s", SaveGame, Some("Minesweeper")),
+        KeyBinding::new("ctrl-r", OpenReplay, Some("Minesweeper")),
+        KeyBinding::new("p", Pause, Some("Minesweeper")),
+        KeyBinding::new("1", DiffBeginner, Some("Minesweeper")),
+        KeyBinding::new("2", DiffIntermediate, Some("Minesweeper")),
+        KeyBinding::new("3", DiffExpert, Some("Minesweeper")),
+        KeyBinding::new("space", ReplayPlayPause, Some("Replay")),
+        KeyBinding::new("left", ReplayStepBack, Some("Replay")),
+        KeyBinding::new("right", ReplayStepForward, Some("Replay")),
+        KeyBinding::new("-", ReplaySlower, Some("Replay")),
+        KeyBinding::new("=", ReplayFaster, Some("Replay")),
+        // Replays use the arrows for their own controls
+        KeyBinding::new("up", CursorUp, Some(PLAYING)),
+        KeyBinding::new("down", CursorDown, Some(PLAYING)),
+        KeyBinding::new("left", CursorLeft, Some(PLAYING)),
+        KeyBinding::new("right", CursorRight, Some(PLAYING)),
+        KeyBinding::new("k", CursorUp, Some(PLAYING)),
+        KeyBinding::new("j", CursorDown, Some(PLAYING)),
+        KeyBinding::new("h", CursorLeft, Some(PLAYING)),
+        KeyBinding::new("l", CursorRight, Some(PLAYING)),
+    ]
+}
+
+fn key_binding(keystrokes: &str, action: KeyAction) -> KeyBinding {
+    match action {
+        KeyAction::Reveal => KeyBinding::new(keystrokes, CursorReveal, Some(PLAYING)),
+        KeyAction::Flag => KeyBinding::new(keystrokes, CursorFlag, Some(PLAYING)),
+        KeyAction::Chord => KeyBinding::new(keystrokes, CursorChord, Some(PLAYING)),
+        KeyAction::NewGame => KeyBinding::new(keystrokes, NewGame, Some("Minesweeper")),
+        KeyAction::Hint => KeyBinding::new(keystrokes, ShowHint, Some("Minesweeper")),
+        KeyAction::Undo => KeyBinding::new(keystrokes, Undo, Some("Minesweeper")),
+    }
+}
+
+/// Replaces every key binding with the fixed keys plus those in `bindings`.
+/// Keys that don't parse are left out, and what was wrong with each returned.
+pub fn bind_keys(bindings: &Bindings, cx: &mut App) -> Vec<String> {
+    let mut keys = fixed_key_bindings();
+    let mut problems = Vec::new();
+    for (action, keystrokes) in bindings.keys.actions() {
+        for keystroke in keystrokes {
+            let parsed = keystroke
+                .split_whitespace()
+                .map(Keystroke::parse)
+                .collect::<Result<Vec<_>, _>>();
+            match parsed {
+                Ok(parsed) if !parsed.is_empty() => keys.push(key_binding(keystroke, action)),
+                Ok(_) => problems.push(format!("An empty key for {:?}", action)),
+                Err(err) => problems.push(format!("\"{}\" for {:?}: {}", keystroke, action, err)),
+            }
+        }
+    }
+    cx.clear_key_bindings();
+    cx.bind_keys(keys);
+    problems
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+fn button(button: MouseButton) -> Option<Button> {
+    match button {
+        MouseButton::Left => Some(Button::Left),
+        MouseButton::Right => Some(Button::Right),
+        MouseButton::Middle => Some(Button::Middle),
+        _ => None,
+    }
+}
+
+impl MinesweeperView {
+    /// Reads the bindings file and applies it, writing the defaults out first
+    /// so there is a file to edit. A damaged file keeps the bindings in use.
+    pub fn load_bindings(&mut self, cx: &mut Context<Self>) {
+        if let Some(path) = bindings_path() {
+            if !path.exists()
+                && let Err(err) = Bindings::default().save(&path)
+            {
+                self.report_error("Could not write key bindings", err, cx);
+            }
+            self.bindings_modified = modified(&path);
+            match Bindings::load(&path) {
+                Ok(bindings) => self.bindings = bindings,
+                Err(err) => {
+                    let detail = format!("{}. The keys in use are kept.", err);
+                    self.report_error("Could not read key bindings", detail, cx);
+                }
+            }
+        }
+        let problems = bind_keys(&self.bindings, cx);
+        if !problems.is_empty() {
+            self.report_error("Some keys were ignored", problems.join("\n"), cx);
+        }
+    }
+
+    /// Reloads the bindings whenever the file changes.
+    pub fn watch_bindings(&mut self, cx: &mut Context<Self>) {
+        let Some(path) = bindings_path() else {
+            return;
+        };
+        self.bindings_watch = Some(cx.spawn(async move |view, cx| {
+            loop {
+                cx.background_executor().timer(WATCH_INTERVAL).await;
+                let changed = modified(&path);
+                let updated = view.update(cx, |view, cx| {
+                    if changed != view.bindings_modified {
+                        view.load_bindings(cx);
+                        cx.notify();
+                    }
+                });
+                if updated.is_err() {
+                    break;
+                }
+            }
+        }));
+    }
+
+    /// Listens for every mouse button on a cell; `release` adds the button-up
+    /// listeners chording needs on numbers.
+    pub fn cell_mouse_listeners(
+        &self,
+        mut cell_div: Div,
+        row: u32,
+        col: u32,
+        release: bool,
+        cx: &Context<Self>,
+    ) -> Div {
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+            cell_div = cell_div.on_mouse_down(
+                button,
+                cx.listener(move |view, event: &MouseDownEvent, _window, cx| {
+                    view.cell_mouse_down(row, col, button, event.click_count, cx);
+                }),
+            );
+            if release {
+                cell_div = cell_div.on_mouse_up(
+                    button,
+                    cx.listener(move |view, _, _window, cx| {
+                        view.cell_mouse_up(row, col, button, cx)
+                    }),
+                );
+            }
+        }
+        cell_div
+    }
+
+    fn cell_mouse_down(
+        &mut self,
+        row: u32,
+        col: u32,
+        button: MouseButton,
+        click_count: usize,
+        cx: &mut Context<Self>,
+    ) {
+        let Some(action) = self.button_action(button) else {
+            return;
+        };
+        let state = self.game.cells[self.game.index(row, col)].state;
+        if state != CellState::Revealed {
+            match action {
+                ButtonAction::Reveal => self.handle_click(row, col, cx),
+                ButtonAction::Flag => self.handle_right_click(row, col, cx),
+                ButtonAction::Chord | ButtonAction::Nothing => {}
+            }
+            return;
+        }
+        // Numbers chord on the chord button, both buttons together, or a double click
+        match action {
+            ButtonAction::Chord => self.handle_chord_start(row, col, cx),
+            ButtonAction::Reveal => {
+                self.reveal_button_down = true;
+                if click_count == 2
+                    || self.flag_button_down
+                    || self.bindings.mouse.left_click_chords
+                {
+                    self.handle_chord_start(row, col, cx);
+                }
+            }
+            ButtonAction::Flag => {
+                self.flag_button_down = true;
+                if self.reveal_button_down {
+                    self.handle_chord_start(row, col, cx);
+                }
+            }
+            ButtonAction::Nothing => {}
+        }
+    }
+
+    fn cell_mouse_up(&mut self, row: u32, col: u32, button: MouseButton, cx: &mut Context<Self>) {
+        match self.button_action(button) {
+            Some(ButtonAction::Reveal) => self.reveal_button_down = false,
+            Some(ButtonAction::Flag) => self.flag_button_down = false,
+            Some(ButtonAction::Chord) => {}
+            Some(ButtonAction::Nothing) | None => return,
+        }
+        // Finishes a chord if one was started here
+        self.handle_chord_end(row, col, cx);
+    }
+
+    fn button_action(&self, pressed: MouseButton) -> Option<ButtonAction> {
+        button(pressed).map(|pressed| self.bindings.mouse.action(pressed))
+    }
+}
@@ -0,0 +1,157 @@
+// Game engine, kept free of any UI so other frontends and tools can reuse it
+pub mod analysis;
+pub mod bindings;
+pub mod clock;
+pub mod game;
+pub mod probability;
+pub mod replay;
+pub mod save;
+pub mod settings;
+pub mod solver;
+pub mod stats;
+pub mod text;
+pub mod topology;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Per-user directory for replays, saved games and other app data.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("minesweep"))
+}
+
+/// One of the app's JSON files. Each has a `version` beside its contents,
+/// read first so a file from a newer release is reported as such rather
+/// than as a parse error.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileFormat {
+    pub name: &'static str, // What the file holds, for messages
+    pub version: u32,       // Written to new files; newer ones are refused
+}
+
+#[derive(Serialize)]
+struct Versioned<'a, T: ?Sized> {
+    version: u32,
+    #[serde(flatten)]
+    contents: &'a T,
+}
+
+#[derive(Deserialize)]
+struct Header {
+    version: u32,
+}
+
+#[derive(Debug)]
+pub enum FileError {
+    Io {
+        name: &'static str,
+        err: io::Error,
+    },
+    Parse {
+        name: &'static str,
+        err: serde_json::Error,
+    },
+    UnsupportedVersion {
+        name: &'static str,
+        found: u32,
+        supported: u32,
+    },
+}
+
+impl fmt::Display for FileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FileError::Io { name, err } => write!(f, "Could not access {}: {}", name, err),
+            FileError::Parse { name, err } => write!(f, "Not a readable {} file: {}", name, err),
+            FileError::UnsupportedVersion {
+                name,
+                found,
+                supported,
+            } => write!(
+                f,
+                "This {} file is format {}, newer than supported ({})",
+                name, found, supported
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            FileError::Io { err, .. } => Some(err),
+            FileError::Parse { err, .. } => Some(err),
+            FileError::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+impl FileFormat {
+    pub const fn new(name: &'static str, version: u32) -> Self {
+        Self { name, version }
+    }
+
+    fn io_error(&self, err: io::Error) -> FileError {
+        FileError::Io {
+            name: self.name,
+            err,
+        }
+    }
+
+    fn parse_error(&self, err: serde_json::Error) -> FileError {
+        FileError::Parse {
+            name: self.name,
+            err,
+        }
+    }
+
+    /// `contents`, which must serialize as a struct, with the version added.
+    pub fn to_json<T: Serialize + ?Sized>(&self, contents: &T) -> String {
+        serde_json::to_string_pretty(&Versioned {
+            version: self.version,
+            contents,
+        })
+        .expect("file contents always serialize")
+    }
+
+    pub fn from_json<T: DeserializeOwned>(&self, json: &str) -> Result<T, FileError> {
+        let header: Header = serde_json::from_str(json).map_err(|err| self.parse_error(err))?;
+        if header.version > self.version {
+            return Err(FileError::UnsupportedVersion {
+                name: self.name,
+                found: header.version,
+                supported: self.version,
+            });
+        }
+        serde_json::from_str(json).map_err(|err| self.parse_error(err))
+    }
+
+    pub fn load<T: DeserializeOwned>(&self, path: &Path) -> Result<T, FileError> {
+        let json = fs::read_to_string(path).map_err(|err| self.io_error(err))?;
+        self.from_json(&json)
+    }
+
+    /// Like `load`, but a missing file gives the default.
+    pub fn load_or_default<T: DeserializeOwned + Default>(
+        &self,
+        path: &Path,
+    ) -> Result<T, FileError> {
+        match fs::read_to_string(path) {
+            Ok(json) => self.from_json(&json),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+            Err(err) => Err(self.io_error(err)),
+        }
+    }
+
+    /// Writes `contents`, creating the directory if needed.
+    pub fn save<T: Serialize + ?Sized>(&self, path: &Path, contents: &T) -> Result<(), FileError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| self.io_error(err))?;
+        }
+        fs::write(path, self.to_json(contents)).map_err(|err| self.io_error(err))
+    }
+}
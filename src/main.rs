@@ -1,39 +1,231 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use gpui::prelude::FluentBuilder;
 use gpui::*;
-use std::time::Duration;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-mod game;
-use game::{Cell, CellContent, CellState, Difficulty, GameStatus, Minesweeper};
+mod cursor;
+mod custom_dialog;
+mod end_panel;
+mod input;
+mod name_dialog;
+mod playback;
+mod seed_dialog;
+mod session;
+mod stats_window;
+mod text_field;
+mod theme;
+use cursor::cursor_overlay;
+use custom_dialog::CustomDialog;
+use minesweep::FileError;
+use minesweep::analysis::{BoardStats, Performance};
+use minesweep::bindings::Bindings;
+use minesweep::game::{Cell, CellContent, CellState, Difficulty, GameStatus, Hint, Minesweeper};
+use minesweep::probability::{ProbabilityGrid, probabilities};
+use minesweep::replay::{Replay, replays_dir};
+use minesweep::settings::{Settings, ThemeName, settings_path};
+use minesweep::stats::{GameRecord, Stats, stats_path};
+use minesweep::topology::{Grid, Neighborhood};
+use playback::Playback;
+use seed_dialog::SeedDialog;
+use theme::{Theme, theme};
 
 actions!(
     minesweeper,
-    [NewGame, DiffBeginner, DiffIntermediate, DiffExpert, Exit]
+    [
+        NewGame,
+        DiffBeginner,
+        DiffIntermediate,
+        DiffExpert,
+        DiffCustom,
+        GameFromSeed,
+        ShowStatistics,
+        Pause,
+        SaveGame,
+        OpenGame,
+        ToggleNoGuess,
+        ToggleHexGrid,
+        ToggleWrap,
+        ToggleMultiMine,
+        RuleClassic,
+        RuleCross,
+        RuleKnight,
+        RuleRadius2,
+        ToggleHeatmap,
+        ToggleTenths,
+        ThemeWin2000,
+        ThemeXp,
+        ThemeDark,
+        ThemeHighContrast,
+        ShowHint,
+        Undo,
+        Redo,
+        CopyBoard,
+        PasteBoard,
+        OpenReplay,
+        ReplayPlayPause,
+        ReplayStepBack,
+        ReplayStepForward,
+        ReplaySlower,
+        ReplayFaster,
+        CursorUp,
+        CursorDown,
+        CursorLeft,
+        CursorRight,
+        CursorReveal,
+        CursorFlag,
+        CursorChord,
+        Exit
+    ]
 );
 
+const CELL_SIZE: f32 = 16.0;
+
+const STATUS_LINE_HEIGHT: f32 = 16.0;
+
+// Window content size for a board; 9x9 gives the original 180x240 window plus the status line
+fn board_window_size(rows: u32, cols: u32, grid: Grid) -> Size<Pixels> {
+    // Odd hex rows are shifted by half a cell
+    let offset = if grid == Grid::Hex {
+        CELL_SIZE / 2.0
+    } else {
+        0.0
+    };
+    size(
+        px(cols as f32 * CELL_SIZE + offset + 36.0),
+        px(rows as f32 * CELL_SIZE + 96.0 + STATUS_LINE_HEIGHT),
+    )
+}
+
+fn dialog_window_options(title: &'static str, content: Size<Pixels>, cx: &App) -> WindowOptions {
+    WindowOptions {
+        window_bounds: Some(WindowBounds::Windowed(Bounds::centered(None, content, cx))),
+        titlebar: Some(TitlebarOptions {
+            title: Some(title.into()),
+            appears_transparent: false,
+            traffic_light_position: None,
+        }),
+        is_resizable: false,
+        is_minimizable: false,
+        ..Default::default()
+    }
+}
+
 struct MinesweeperView {
+    focus_handle: FocusHandle, // Keeps key bindings working without a focused child
     game: Minesweeper,
     difficulty: Difficulty,
     timer_handle: Option<Task<()>>,
+    shown_time: String, // Timer text last drawn, so ticks only redraw when it changes
+    tenths: bool,       // Timer shows tenths of a second
     chord_target: Option<(u32, u32)>, // Track which cell is being chorded (pressed)
-    flashing_cells: Vec<(u32, u32)>,  // For visual feedback on failed chords
-    left_mouse_down: bool,
-    right_mouse_down: bool,
+    flashing_cells: Vec<(u32, u32)>, // For visual feedback on failed chords
+    reveal_button_down: bool, // Held on a number, for chording with both buttons
+    flag_button_down: bool,
+    bindings: Bindings,
+    bindings_modified: Option<SystemTime>, // Of the file last loaded, to spot edits
+    bindings_watch: Option<Task<()>>,
+    settings: Settings,
+    no_guess: bool, // Applied to every new game
+    grid: Grid,     // Likewise
+    wrap: bool,
+    rule: Neighborhood,
+    multi_mine: bool,
+    heatmap: bool,
+    probabilities: Option<ProbabilityGrid>, // Cached for the heatmap, cleared on every move
+    hint: Option<Hint>,                     // Highlighted until the next move
+    finished: bool,                         // Whether the game was over at the last board change
+    board_stats: Option<BoardStats>,        // Worked out once the game is over
+    performance: Option<Performance>,       // End-of-game panel, until it is clicked away
+    replay_path: Option<PathBuf>,           // Rewritten if the game ends again after an undo
+    stats: Option<Stats>, // None if the file couldn't be read, so it is left alone
+    stats_entry: Option<usize>, // Like replay_path, for this game's statistics entry
+    playback: Option<Playback>, // Set while watching a replay; clicks are ignored then
+    generating: Option<Task<()>>, // Searching for a no-guess layout; clicks wait for it
+    cursor: Option<(u32, u32)>, // Keyboard cursor, once the keys have been used
+    errors: Vec<(String, String)>, // Failures met away from the window, shown on the next draw
+    _activation: Subscription, // Pauses the game when the window goes to the background
 }
 
 impl MinesweeperView {
-    fn new(cx: &mut Context<Self>) -> Self {
+    fn new(window: &mut Window, cx: &mut Context<Self>) -> Self {
         let difficulty = Difficulty::Beginner;
+        let focus_handle = cx.focus_handle();
+        focus_handle.focus(window);
+        // Minimizing deactivates the window too, so this covers both
+        let _activation = cx.observe_window_activation(window, |view, window, cx| {
+            if !window.is_window_active() && view.game.status == GameStatus::Playing {
+                view.game.pause();
+                cx.notify();
+            }
+        });
+        let mut errors = Vec::new();
+        let settings = load_settings().unwrap_or_else(|err| {
+            let detail = format!("{}. The default settings are used instead.", err);
+            errors.push(("Could not read settings".to_string(), detail));
+            Settings::default()
+        });
+        // Left as None, the file isn't overwritten and can still be fixed
+        let stats = load_stats()
+            .inspect_err(|err| {
+                let detail = format!("{}. Games won't be recorded this time.", err);
+                errors.push(("Could not read statistics".to_string(), detail));
+            })
+            .ok();
+        cx.set_global(Theme::named(settings.theme));
         let mut view = Self {
+            focus_handle,
             game: Minesweeper::new(difficulty),
             difficulty,
             timer_handle: None,
+            shown_time: String::new(),
+            tenths: false,
             chord_target: None,
             flashing_cells: Vec::new(),
-            left_mouse_down: false,
-            right_mouse_down: false,
+            reveal_button_down: false,
+            flag_button_down: false,
+            bindings: Bindings::default(),
+            bindings_modified: None,
+            bindings_watch: None,
+            settings,
+            no_guess: false,
+            grid: Grid::Square,
+            wrap: false,
+            rule: Neighborhood::Classic,
+            multi_mine: false,
+            heatmap: false,
+            probabilities: None,
+            hint: None,
+            finished: false,
+            board_stats: None,
+            performance: None,
+            replay_path: None,
+            stats,
+            stats_entry: None,
+            playback: None,
+            generating: None,
+            cursor: None,
+            errors,
+            _activation,
         };
         view.start_timer(cx);
+        view.load_bindings(cx);
+        view.watch_bindings(cx);
+        cx.set_menus(app_menus(&view));
+
+        // Keep an unfinished game however the app goes away
+        let this = cx.entity().downgrade();
+        window.on_window_should_close(cx, move |window, cx| {
+            this.update(cx, |view, cx| view.close_window(window, cx))
+                .unwrap_or(true)
+        });
+        // Nothing can be shown while quitting; closing the window has already asked
+        cx.on_app_quit(|view, _| {
+            view.autosave().ok();
+            async {}
+        })
+        .detach();
         view
     }
 
@@ -48,17 +240,16 @@ impl MinesweeperView {
                     loop {
                         cx_owned
                             .background_executor()
-                            .timer(Duration::from_secs(1))
+                            .timer(Duration::from_millis(100))
                             .await;
                         if view
                             .update(
                                 &mut cx_owned,
                                 |view: &mut MinesweeperView, cx: &mut Context<MinesweeperView>| {
-                                    if view.game.status == GameStatus::Playing {
-                                        view.game.elapsed_seconds += 1;
-                                        if view.game.elapsed_seconds > 999 {
-                                            view.game.elapsed_seconds = 999;
-                                        }
+                                    // The game keeps the time; this only redraws it
+                                    let time = view.time_display();
+                                    if time != view.shown_time {
+                                        view.shown_time = time;
                                         cx.notify();
                                     }
                                 },
@@ -73,27 +264,167 @@ impl MinesweeperView {
         ));
     }
 
+    // The LCD timer: whole seconds like the original, or with tenths
+    fn time_display(&self) -> String {
+        let elapsed = self.game.elapsed();
+        let seconds = elapsed.as_secs().min(999);
+        if self.tenths {
+            format!("{:03}.{}", seconds, elapsed.subsec_millis() / 100)
+        } else {
+            format!("{:03}", seconds)
+        }
+    }
+
+    // Drops everything derived from the previous board state
+    fn board_changed(&mut self, cx: &mut Context<Self>) {
+        self.probabilities = None;
+        self.hint = None;
+        self.note_heatmap_use();
+        // Help seen in a replay belongs to the recorded game, not the last one played
+        if self.game.is_assisted() && self.playback.is_none() {
+            self.forget_stats_entry(cx);
+        }
+        let finished = matches!(self.game.status, GameStatus::Won | GameStatus::Lost);
+        if finished && self.board_stats.is_none() {
+            self.board_stats = self.game.analyze();
+        }
+        if finished && !self.finished {
+            self.performance = self.game.performance();
+            if self.playback.is_none() {
+                self.save_replay(cx);
+                self.record_stats(cx);
+            }
+        } else if !finished {
+            self.performance = None;
+        }
+        self.finished = finished;
+        cx.notify();
+    }
+
+    fn save_replay(&mut self, cx: &mut Context<Self>) {
+        let Some(replay) = Replay::from_game(&self.game) else {
+            return;
+        };
+        let path = match &self.replay_path {
+            Some(path) => path.clone(),
+            None => {
+                let Some(dir) = replays_dir() else {
+                    return;
+                };
+                dir.join(replay.file_name())
+            }
+        };
+        match replay.save(&path) {
+            Ok(()) => self.replay_path = Some(path),
+            Err(err) => self.report_error("Could not save replay", err, cx),
+        }
+    }
+
+    // Queues a failure for the next draw, for places with no window at hand
+    fn report_error(&mut self, message: &str, err: impl ToString, cx: &mut Context<Self>) {
+        self.errors.push((message.to_string(), err.to_string()));
+        cx.notify();
+    }
+
+    fn record_stats(&mut self, cx: &mut Context<Self>) {
+        let Some(stats) = &mut self.stats else {
+            return;
+        };
+        let Some(record) = GameRecord::from_game(&self.game) else {
+            return;
+        };
+        let index = stats.add(record);
+        self.stats_entry = Some(index);
+        let unnamed_record = stats.is_best_time(index) && stats.games()[index].name.is_empty();
+        self.save_stats(cx);
+        if unnamed_record {
+            self.ask_record_name(index, cx);
+        }
+    }
+
+    // Help taken after the end, such as an undo, means the game no longer counts
+    fn forget_stats_entry(&mut self, cx: &mut Context<Self>) {
+        let (Some(stats), Some(index)) = (&mut self.stats, self.stats_entry.take()) else {
+            return;
+        };
+        stats.remove(index);
+        self.save_stats(cx);
+    }
+
+    // Neither a replay nor the search for a first layout takes moves
+    fn accepts_moves(&self) -> bool {
+        self.playback.is_none() && self.generating.is_none()
+    }
+
     fn handle_click(&mut self, row: u32, col: u32, cx: &mut Context<Self>) {
-        self.game.reveal(row, col);
+        if !self.accepts_moves() {
+            return;
+        }
+        if self.game.needs_layout_search() && self.game.contains(row, col) {
+            self.generate_layout(row, col, cx);
+            return;
+        }
+        if self.game.reveal(row, col).is_ok() {
+            self.board_changed(cx);
+        }
+    }
+
+    // A no-guess layout can take seconds on big boards, so the search runs
+    // off the UI thread and the click is made once it is done
+    fn generate_layout(&mut self, row: u32, col: u32, cx: &mut Context<Self>) {
+        let mut game = self.game.clone();
+        let search = cx.background_spawn(async move {
+            game.lay_mines_for(row, col);
+            game
+        });
+        self.generating = Some(cx.spawn(async move |view, cx| {
+            let game = search.await;
+            view.update(cx, |view, cx| {
+                view.generating = None;
+                view.game = game;
+                view.handle_click(row, col, cx);
+            })
+            .ok();
+        }));
         cx.notify();
     }
 
     fn handle_right_click(&mut self, row: u32, col: u32, cx: &mut Context<Self>) {
-        self.game.toggle_flag(row, col);
-        cx.notify();
+        if !self.accepts_moves() {
+            return;
+        }
+        if self.game.toggle_flag(row, col).is_ok() {
+            self.board_changed(cx);
+        }
+    }
+
+    // Like moves, undo and redo would restart the clock under the pause cover
+    fn undo(&mut self, cx: &mut Context<Self>) {
+        if self.accepts_moves() && !self.is_paused() && self.game.undo() {
+            self.board_changed(cx);
+        }
+    }
+
+    fn redo(&mut self, cx: &mut Context<Self>) {
+        if self.accepts_moves() && !self.is_paused() && self.game.redo() {
+            self.board_changed(cx);
+        }
     }
 
     fn handle_chord_start(&mut self, row: u32, col: u32, cx: &mut Context<Self>) {
+        if !self.accepts_moves() {
+            return;
+        }
         self.chord_target = Some((row, col));
         cx.notify();
     }
 
     fn handle_chord_end(&mut self, row: u32, col: u32, cx: &mut Context<Self>) {
         if self.chord_target == Some((row, col)) {
-            let success = self.game.chord(row, col);
+            let outcome = self.game.chord(row, col);
             self.chord_target = None;
 
-            if !success {
+            if outcome.is_ok_and(|outcome| outcome.is_empty()) {
                 // Flash neighbors
                 let neighbors = self.game.neighbors(row, col);
                 self.flashing_cells = neighbors
@@ -123,7 +454,7 @@ impl MinesweeperView {
                 .detach();
             }
 
-            cx.notify();
+            self.board_changed(cx);
         }
     }
 
@@ -134,82 +465,412 @@ impl MinesweeperView {
         }
     }
 
-    fn reset(&mut self, difficulty: Difficulty, cx: &mut Context<Self>) {
+    fn reset(&mut self, difficulty: Difficulty, window: &mut Window, cx: &mut Context<Self>) {
+        self.start_game(difficulty, Minesweeper::new(difficulty), window, cx);
+    }
+
+    fn start_seeded(&mut self, seed: u64, window: &mut Window, cx: &mut Context<Self>) {
+        let difficulty = self.difficulty;
+        self.start_game(
+            difficulty,
+            Minesweeper::with_seed(difficulty, seed),
+            window,
+            cx,
+        );
+    }
+
+    fn start_game(
+        &mut self,
+        difficulty: Difficulty,
+        mut game: Minesweeper,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        let old_size = self.window_size();
+        // Games resumed after the first click keep the mode they were generated with
+        if game.status == GameStatus::NotStarted {
+            game.no_guess = self.no_guess;
+            game.grid = self.grid;
+            game.wrap = self.wrap;
+            game.rule = self.rule;
+            game.multi_mine = self.multi_mine;
+        }
         self.difficulty = difficulty;
-        self.game.reset(difficulty);
+        self.cursor = self.cursor.filter(|&(row, col)| game.contains(row, col));
+        self.game = game;
+        self.replay_path = None;
+        self.stats_entry = None;
+        self.playback = None;
+        self.generating = None;
+        self.board_stats = None;
+        // A game opened already won or lost was counted when it ended, if at
+        // all; it is only shown, with its summary
+        self.finished = matches!(self.game.status, GameStatus::Won | GameStatus::Lost);
+        self.performance = self.game.performance().filter(|_| self.finished);
+        self.board_changed(cx);
+        self.fit_window(old_size, window);
+    }
+
+    // Resize window based on difficulty and replay controls
+    fn fit_window(&self, old_size: Size<Pixels>, window: &mut Window) {
+        let size = self.window_size();
+        if size != old_size {
+            window.resize(size);
+        }
+    }
+
+    fn open_custom_dialog(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(game_window) = window.window_handle().downcast::<MinesweeperView>() else {
+            return;
+        };
+        let current = self.difficulty;
+        let options = dialog_window_options("Custom Field", size(px(180.0), px(160.0)), cx);
+        cx.open_window(options, |window, cx| {
+            cx.new(|cx| CustomDialog::new(current, game_window, window, cx))
+        })
+        .ok();
+    }
+
+    fn toggle_no_guess(&mut self, cx: &mut Context<Self>) {
+        self.no_guess = !self.no_guess;
+        // Mines are placed on the first click, so an untouched board can still switch
+        if self.game.status == GameStatus::NotStarted {
+            self.game.no_guess = self.no_guess;
+        }
+        cx.set_menus(app_menus(self));
+        cx.notify();
+    }
+
+    fn toggle_wrap(&mut self, cx: &mut Context<Self>) {
+        self.wrap = !self.wrap;
+        if self.game.status == GameStatus::NotStarted {
+            self.game.wrap = self.wrap;
+        }
+        cx.set_menus(app_menus(self));
         cx.notify();
+    }
 
-        // Resize window based on difficulty
-        // let (rows, cols, _) = difficulty.config();
-        // let width = cols as f32 * 24.0 + 40.0; // Approximate
-        // let height = rows as f32 * 24.0 + 100.0; // Approximate
+    fn toggle_multi_mine(&mut self, cx: &mut Context<Self>) {
+        self.multi_mine = !self.multi_mine;
+        if self.game.status == GameStatus::NotStarted {
+            self.game.multi_mine = self.multi_mine;
+        }
+        cx.set_menus(app_menus(self));
+        cx.notify();
+    }
 
-        // TODO: Resize window not supported in current gpui version or requires different API
-        /*
-        cx.resize_window(WindowSize {
-            width: px(width),
-            height: px(height),
-        });
-        */
+    fn set_rule(&mut self, rule: Neighborhood, cx: &mut Context<Self>) {
+        self.rule = rule;
+        if self.game.status == GameStatus::NotStarted {
+            self.game.rule = rule;
+        }
+        cx.set_menus(app_menus(self));
+        cx.notify();
+    }
+
+    // Switching grids needs a fresh board, and a differently sized window
+    fn toggle_hex_grid(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        self.grid = if self.grid == Grid::Hex {
+            Grid::Square
+        } else {
+            Grid::Hex
+        };
+        cx.set_menus(app_menus(self));
+        self.reset(self.difficulty, window, cx);
+    }
+
+    fn show_hint(&mut self, cx: &mut Context<Self>) {
+        if !self.accepts_moves() || self.is_paused() {
+            return;
+        }
+        self.hint = self.game.hint();
+        cx.notify();
+    }
+
+    fn status_text(&self) -> String {
+        if let Some(playback) = &self.playback {
+            return self.replay_status_text(playback);
+        }
+        if self.is_paused() {
+            return "Paused · press P or click the board to resume".to_string();
+        }
+        if self.generating.is_some() {
+            return "Finding a board that needs no guessing…".to_string();
+        }
+        match (self.game.status, self.hint) {
+            (GameStatus::Won, _) => {
+                let mut text = format!("Cleared in {:.3}s", self.game.elapsed().as_secs_f32());
+                if let Some(stats) = self.board_stats {
+                    text.push_str(&format!(" · 3BV {}", stats.bbbv));
+                }
+                if self.game.hints_used > 0 {
+                    text.push_str(&format!(" · {} hints used", self.game.hints_used));
+                }
+                if self.game.undos_used > 0 {
+                    text.push_str(&format!(" · {} undos", self.game.undos_used));
+                }
+                if self.game.heatmap_used {
+                    text.push_str(" · heatmap shown");
+                }
+                text
+            }
+            (GameStatus::Lost, _) => match self.board_stats {
+                Some(stats) => format!("Seed {} · 3BV {}", self.game.seed, stats.bbbv),
+                None => format!("Seed {}", self.game.seed),
+            },
+            (_, Some(Hint::Safe { .. })) => "Hint: green cell is safe".to_string(),
+            (
+                _,
+                Some(Hint::Guess {
+                    mine_probability: p,
+                    ..
+                }),
+            ) => {
+                format!("Best guess: {:.1}% mine", p * 100.0)
+            }
+            _ => {
+                let mut text = format!("Seed {}", self.game.seed);
+                if self.game.no_guess {
+                    if self.game.status == GameStatus::NotStarted || self.game.guess_free {
+                        text.push_str(" · no guessing");
+                    } else {
+                        text.push_str(" · may need a guess");
+                    }
+                }
+                text
+            }
+        }
+    }
+
+    // Stops the clock and hides the board; any click on the cover resumes
+    fn toggle_pause(&mut self, cx: &mut Context<Self>) {
+        if self.playback.is_some() {
+            return;
+        }
+        if self.game.is_paused() {
+            self.game.resume();
+        } else {
+            self.game.pause();
+        }
+        cx.notify();
+    }
+
+    fn is_paused(&self) -> bool {
+        self.playback.is_none() && self.game.is_paused()
+    }
+
+    fn toggle_tenths(&mut self, cx: &mut Context<Self>) {
+        self.tenths = !self.tenths;
+        cx.set_menus(app_menus(self));
+        cx.notify();
+    }
+
+    fn toggle_heatmap(&mut self, cx: &mut Context<Self>) {
+        self.heatmap = !self.heatmap;
+        self.note_heatmap_use();
+        cx.set_menus(app_menus(self));
+        cx.notify();
+    }
+
+    // Exact odds give away safe cells like a hint does, so a game played with
+    // them on counts as assisted. Before the first click they are the same
+    // everywhere, and a replay only shows the moves someone else made.
+    fn note_heatmap_use(&mut self) {
+        if self.heatmap
+            && !self.game.multi_mine
+            && self.game.status == GameStatus::Playing
+            && self.playback.is_none()
+        {
+            self.game.heatmap_used = true;
+        }
+    }
+
+    fn open_seed_dialog(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(game_window) = window.window_handle().downcast::<MinesweeperView>() else {
+            return;
+        };
+        let current = self.game.seed;
+        let options = dialog_window_options("Game from Seed", size(px(180.0), px(120.0)), cx);
+        cx.open_window(options, |window, cx| {
+            cx.new(|cx| SeedDialog::new(current, game_window, window, cx))
+        })
+        .ok();
     }
 }
 
-// Colors
-fn color_gray() -> Rgba {
-    rgba(0xC0C0C0FF)
-} // #C0C0C0
-fn color_white() -> Rgba {
-    rgba(0xFFFFFFFF)
+// Heatmap tint for a hidden cell: green when safe, through yellow, to red for a sure mine
+fn heat_color(p: f64, face: Rgba) -> Rgba {
+    let p = p.clamp(0.0, 1.0) as f32;
+    let (r, g) = if p < 0.5 {
+        (p * 2.0, 1.0)
+    } else {
+        (1.0, (1.0 - p) * 2.0)
+    };
+    // Blend 50% over the normal face so the board still reads as unrevealed
+    Rgba {
+        r: (face.r + r) / 2.0,
+        g: (face.g + g) / 2.0,
+        b: face.b / 2.0,
+        a: 1.0,
+    }
 }
-fn color_dark_gray() -> Rgba {
-    rgba(0x808080FF)
-} // #808080
-fn color_black() -> Rgba {
-    rgba(0x000000FF)
+
+// Faint dashed edge around a board whose edges wrap; it has no listeners, so
+// clicks go through to the cells
+fn wrap_overlay(color: Rgba) -> impl IntoElement {
+    div()
+        .absolute()
+        .top_0()
+        .left_0()
+        .size_full()
+        .border_1()
+        .border_dashed()
+        .border_color(color)
 }
-fn color_red() -> Rgba {
-    rgba(0xFF0000FF)
+
+impl MinesweeperView {
+    // Hides every cell while paused, so the board can't be studied off the clock
+    fn render_pause_cover(&self, cx: &mut Context<Self>) -> impl IntoElement {
+        div()
+            .absolute()
+            .top_0()
+            .left_0()
+            .size_full()
+            .bg(theme(cx).face)
+            .flex()
+            .justify_center()
+            .items_center()
+            .text_size(px(14.0))
+            .font_weight(FontWeight::BOLD)
+            .text_color(theme(cx).dim_text)
+            .occlude()
+            .on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.toggle_pause(cx)),
+            )
+            .child("Paused")
+    }
+}
+
+// Pulsing border drawn over a hinted cell
+fn hint_overlay(color: Rgba) -> impl IntoElement {
+    div()
+        .absolute()
+        .top_0()
+        .left_0()
+        .size_full()
+        .border_2()
+        .border_color(color)
+        .with_animation(
+            "hint-pulse",
+            Animation::new(Duration::from_millis(900))
+                .repeat()
+                .with_easing(pulsating_between(0.2, 1.0)),
+            move |overlay, delta| overlay.border_color(Rgba { a: delta, ..color }),
+        )
+}
+
+fn load_stats() -> Result<Stats, FileError> {
+    match stats_path() {
+        Some(path) => Stats::load(&path),
+        None => Ok(Stats::default()),
+    }
+}
+
+fn load_settings() -> Result<Settings, FileError> {
+    match settings_path() {
+        Some(path) => Settings::load(&path),
+        None => Ok(Settings::default()),
+    }
+}
+
+fn show_error(window: &mut Window, message: &str, detail: &str, cx: &mut App) {
+    // Only one answer, so nothing to wait for
+    drop(window.prompt(PromptLevel::Critical, message, Some(detail), &["OK"], cx));
 }
 
 // Helper for bevels
-fn bevel_raised(content: Div) -> Div {
+fn bevel_raised(theme: &Theme, content: Div) -> Div {
     // Simulate raised bevel: Light Top/Left, Dark Bottom/Right (3px for window/panels)
-    div().bg(color_dark_gray()).pb(px(3.0)).pr(px(3.0)).child(
+    div().bg(theme.shadow).pb(px(3.0)).pr(px(3.0)).child(
         div()
-            .bg(color_white())
+            .bg(theme.highlight)
             .pt(px(3.0))
             .pl(px(3.0))
-            .child(content.bg(color_gray())),
+            .child(content.bg(theme.face)),
     )
 }
 
-fn bevel_sunken(content: Div) -> Div {
-    // Simulate sunken bevel: Dark Top/Left, White Bottom/Right (3px)
-    div().bg(color_white()).pb(px(3.0)).pr(px(3.0)).child(
+fn bevel_sunken(theme: &Theme, content: Div) -> Div {
+    // Simulate sunken bevel: Dark Top/Left, Light Bottom/Right (3px)
+    div().bg(theme.highlight).pb(px(3.0)).pr(px(3.0)).child(
         div()
-            .bg(color_dark_gray())
+            .bg(theme.shadow)
             .pt(px(3.0))
             .pl(px(3.0))
-            .child(content.bg(color_gray())),
+            .child(content.bg(theme.face)),
     )
 }
 
-fn bevel_sunken_thin(content: Div) -> Div {
+fn bevel_button(theme: &Theme, label: impl Into<SharedString>) -> Div {
+    bevel_button_sized(theme, label, px(56.0))
+}
+
+fn bevel_button_sized(theme: &Theme, label: impl Into<SharedString>, min_width: Pixels) -> Div {
+    // Push button with the same 2px raised bevel as the smiley
+    div().bg(theme.shadow).pb(px(2.0)).pr(px(2.0)).child(
+        div().bg(theme.highlight).pt(px(2.0)).pl(px(2.0)).child(
+            div()
+                .min_w(min_width)
+                .h(px(20.0))
+                .px(px(6.0))
+                .bg(theme.face)
+                .text_color(theme.text)
+                .flex()
+                .justify_center()
+                .items_center()
+                .text_size(px(12.0))
+                .child(label.into()),
+        ),
+    )
+}
+
+fn bevel_sunken_thin(theme: &Theme, content: Div) -> Div {
     // Thinner sunken bevel for counters (1px or 2px)
-    // The background inside this bevel should be BLACK.
-    div().bg(color_white()).pb(px(1.0)).pr(px(1.0)).child(
+    div().bg(theme.highlight).pb(px(1.0)).pr(px(1.0)).child(
         div()
-            .bg(color_dark_gray())
+            .bg(theme.shadow)
             .pt(px(1.0))
             .pl(px(1.0))
-            .child(content.bg(color_black())), // Ensure content bg is black
+            .child(content.bg(theme.counter)),
     )
 }
 
 impl Render for MinesweeperView {
-    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        // One prompt for everything queued, as a second would replace the first
+        if let Some((message, _)) = self.errors.first() {
+            let message = message.clone();
+            let detail = self
+                .errors
+                .drain(..)
+                .map(|(_, detail)| detail)
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            window.defer(cx, move |window, cx| {
+                show_error(window, &message, &detail, cx)
+            });
+        }
         let (rows, cols) = (self.game.rows, self.game.cols);
         let status = self.game.status;
+        let theme = theme(cx).clone();
+
+        let in_progress = matches!(status, GameStatus::NotStarted | GameStatus::Playing);
+        // Probabilities assume one mine per cell, so stacked boards go without
+        let heatmap = self.heatmap && in_progress && !self.game.multi_mine;
+        if heatmap && self.probabilities.is_none() {
+            self.probabilities = Some(probabilities(&self.game));
+        }
 
         // Collect grid children using for loops to avoid closure capturing issues
         let mut grid = Vec::with_capacity(rows as usize);
@@ -218,30 +879,128 @@ impl Render for MinesweeperView {
             for c in 0..cols {
                 let idx = (r * cols + c) as usize;
                 let cell = &self.game.cells[idx];
-                row_children.push(self.render_cell(r, c, cell, cx));
+                row_children.push(
+                    self.render_cell(r, c, cell, cx)
+                        .when(self.cursor == Some((r, c)), |cell_div| {
+                            cell_div.relative().child(cursor_overlay(theme.cursor))
+                        }),
+                );
             }
-            grid.push(div().flex().flex_row().children(row_children));
+            let row_div = div().flex().flex_row().children(row_children);
+            // Offset rows: each hex sits between the two cells above it
+            grid.push(
+                row_div.when(self.game.grid == Grid::Hex && r % 2 == 1, |row_div| {
+                    row_div.pl(px(CELL_SIZE / 2.0))
+                }),
+            );
         }
 
         let mines_left = self.game.mines as i32 - self.game.flags_placed as i32;
-        let mines_display = format!("{:03}", mines_left.max(-99).min(999));
-        let time_display = format!("{:03}", self.game.elapsed_seconds);
+        let mines_display = format!("{:03}", mines_left.clamp(-99, 999));
+        let time_display = self.time_display();
+
+        let status_line = self.status_text();
 
         div()
-            .key_context("Minesweeper")
+            .key_context(if self.playback.is_some() {
+                "Minesweeper Replay"
+            } else {
+                "Minesweeper"
+            })
+            .track_focus(&self.focus_handle)
+            .on_action(
+                cx.listener(|view, _: &NewGame, window, cx| {
+                    view.reset(view.difficulty, window, cx)
+                }),
+            )
+            .on_action(cx.listener(|view, _: &DiffBeginner, window, cx| {
+                view.reset(Difficulty::Beginner, window, cx)
+            }))
+            .on_action(cx.listener(|view, _: &DiffIntermediate, window, cx| {
+                view.reset(Difficulty::Intermediate, window, cx)
+            }))
+            .on_action(cx.listener(|view, _: &DiffExpert, window, cx| {
+                view.reset(Difficulty::Expert, window, cx)
+            }))
             .on_action(
-                cx.listener(|view, _: &NewGame, _window, cx| view.reset(view.difficulty, cx)),
+                cx.listener(|view, _: &DiffCustom, window, cx| view.open_custom_dialog(window, cx)),
             )
-            .on_action(cx.listener(|view, _: &DiffBeginner, _window, cx| {
-                view.reset(Difficulty::Beginner, cx)
+            .on_action(
+                cx.listener(|view, _: &GameFromSeed, window, cx| view.open_seed_dialog(window, cx)),
+            )
+            .on_action(cx.listener(|view, _: &SaveGame, window, cx| view.save_game(window, cx)))
+            .on_action(
+                cx.listener(|view, _: &ShowStatistics, _window, cx| view.open_statistics(cx)),
+            )
+            .on_action(cx.listener(|view, _: &OpenGame, window, cx| view.open_game(window, cx)))
+            .on_action(cx.listener(|view, _: &ToggleNoGuess, _window, cx| view.toggle_no_guess(cx)))
+            .on_action(
+                cx.listener(|view, _: &ToggleHexGrid, window, cx| view.toggle_hex_grid(window, cx)),
+            )
+            .on_action(cx.listener(|view, _: &ToggleWrap, _window, cx| view.toggle_wrap(cx)))
+            .on_action(
+                cx.listener(|view, _: &ToggleMultiMine, _window, cx| view.toggle_multi_mine(cx)),
+            )
+            .on_action(cx.listener(|view, _: &RuleClassic, _window, cx| {
+                view.set_rule(Neighborhood::Classic, cx)
             }))
-            .on_action(cx.listener(|view, _: &DiffIntermediate, _window, cx| {
-                view.reset(Difficulty::Intermediate, cx)
+            .on_action(cx.listener(|view, _: &RuleCross, _window, cx| {
+                view.set_rule(Neighborhood::Cross, cx)
+            }))
+            .on_action(cx.listener(|view, _: &RuleKnight, _window, cx| {
+                view.set_rule(Neighborhood::Knight, cx)
+            }))
+            .on_action(cx.listener(|view, _: &RuleRadius2, _window, cx| {
+                view.set_rule(Neighborhood::Radius2, cx)
+            }))
+            .on_action(cx.listener(|view, _: &ToggleHeatmap, _window, cx| view.toggle_heatmap(cx)))
+            .on_action(cx.listener(|view, _: &ToggleTenths, _window, cx| view.toggle_tenths(cx)))
+            .on_action(cx.listener(|view, _: &ThemeWin2000, _window, cx| {
+                view.set_theme(ThemeName::Win2000, cx)
+            }))
+            .on_action(
+                cx.listener(|view, _: &ThemeXp, _window, cx| view.set_theme(ThemeName::Xp, cx)),
+            )
+            .on_action(
+                cx.listener(|view, _: &ThemeDark, _window, cx| view.set_theme(ThemeName::Dark, cx)),
+            )
+            .on_action(cx.listener(|view, _: &ThemeHighContrast, _window, cx| {
+                view.set_theme(ThemeName::HighContrast, cx)
             }))
+            .on_action(cx.listener(|view, _: &Pause, _window, cx| view.toggle_pause(cx)))
+            .on_action(cx.listener(|view, _: &CursorUp, _window, cx| view.move_cursor(-1, 0, cx)))
+            .on_action(cx.listener(|view, _: &CursorDown, _window, cx| view.move_cursor(1, 0, cx)))
+            .on_action(cx.listener(|view, _: &CursorLeft, _window, cx| view.move_cursor(0, -1, cx)))
+            .on_action(cx.listener(|view, _: &CursorRight, _window, cx| view.move_cursor(0, 1, cx)))
+            .on_action(cx.listener(|view, _: &CursorReveal, _window, cx| view.cursor_reveal(cx)))
+            .on_action(cx.listener(|view, _: &CursorFlag, _window, cx| view.cursor_flag(cx)))
+            .on_action(cx.listener(|view, _: &CursorChord, _window, cx| view.cursor_chord(cx)))
+            .on_action(cx.listener(|view, _: &ShowHint, _window, cx| view.show_hint(cx)))
+            .on_action(cx.listener(|view, _: &Undo, _window, cx| view.undo(cx)))
+            .on_action(cx.listener(|view, _: &Redo, _window, cx| view.redo(cx)))
+            .on_action(cx.listener(|view, _: &CopyBoard, _window, cx| view.copy_board(cx)))
+            .on_action(cx.listener(|view, _: &PasteBoard, window, cx| view.paste_board(window, cx)))
+            .on_action(cx.listener(|view, _: &OpenReplay, window, cx| view.open_replay(window, cx)))
             .on_action(
-                cx.listener(|view, _: &DiffExpert, _window, cx| view.reset(Difficulty::Expert, cx)),
+                cx.listener(|view, _: &ReplayPlayPause, _window, cx| view.toggle_playing(cx)),
             )
-            .on_action(cx.listener(|_, _: &Exit, _window, cx| cx.quit()))
+            .on_action(
+                cx.listener(|view, _: &ReplayStepBack, _window, cx| view.step_replay(false, cx)),
+            )
+            .on_action(
+                cx.listener(|view, _: &ReplayStepForward, _window, cx| view.step_replay(true, cx)),
+            )
+            .on_action(
+                cx.listener(|view, _: &ReplaySlower, _window, cx| view.change_speed(false, cx)),
+            )
+            .on_action(
+                cx.listener(|view, _: &ReplayFaster, _window, cx| view.change_speed(true, cx)),
+            )
+            .on_action(cx.listener(|view, _: &Exit, window, cx| {
+                if view.close_window(window, cx) {
+                    cx.quit();
+                }
+            }))
             .on_mouse_up(
                 MouseButton::Left,
                 cx.listener(|view, _, _window, cx| view.handle_chord_cancel(cx)),
@@ -250,14 +1009,19 @@ impl Render for MinesweeperView {
                 MouseButton::Middle,
                 cx.listener(|view, _, _window, cx| view.handle_chord_cancel(cx)),
             )
+            .on_mouse_up(
+                MouseButton::Right,
+                cx.listener(|view, _, _window, cx| view.handle_chord_cancel(cx)),
+            )
             .flex()
             .flex_col()
-            .bg(color_gray())
+            .bg(theme.face)
             .w_full() // Ensure it fills the width
             .h_full() // Ensure it fills the height
             .p(px(6.0))
             .gap(px(6.0))
             .child(bevel_raised(
+                &theme,
                 div()
                     .flex()
                     .flex_col()
@@ -266,6 +1030,7 @@ impl Render for MinesweeperView {
                     .child(
                         // Header
                         bevel_sunken(
+                            &theme,
                             // Use thick bevel for the header container? Actually usually header and board are separate sunken areas.
                             // In Win2000, there's just a sunken border around the board, and the counters are sunken.
                             // The container holding counters is FLUSH with the gray background.
@@ -277,8 +1042,9 @@ impl Render for MinesweeperView {
                                 .child(
                                     // Mine Counter
                                     bevel_sunken_thin(
+                                        &theme,
                                         div()
-                                            .text_color(color_red())
+                                            .text_color(theme.counter_text)
                                             .font_weight(FontWeight::BOLD)
                                             .text_size(px(24.0))
                                             .font_family("Courier New") // Monospace
@@ -290,36 +1056,41 @@ impl Render for MinesweeperView {
                                     div().w(px(26.0)).h(px(26.0)).child(
                                         // Make the button itself a bevel (raised)
                                         // Button usually has 2px bevel
-                                        div().bg(color_dark_gray()).pb(px(2.0)).pr(px(2.0)).child(
-                                            div().bg(color_white()).pt(px(2.0)).pl(px(2.0)).child(
-                                                div()
-                                                    .w(px(22.0))
-                                                    .h(px(22.0))
-                                                    .bg(color_gray())
-                                                    .flex()
-                                                    .justify_center()
-                                                    .items_center()
-                                                    .on_mouse_down(
-                                                        MouseButton::Left,
-                                                        cx.listener(|view, _, _window, cx| {
-                                                            let d = view.difficulty;
-                                                            view.reset(d, cx);
+                                        div().bg(theme.shadow).pb(px(2.0)).pr(px(2.0)).child(
+                                            div()
+                                                .bg(theme.highlight)
+                                                .pt(px(2.0))
+                                                .pl(px(2.0))
+                                                .child(
+                                                    div()
+                                                        .w(px(22.0))
+                                                        .h(px(22.0))
+                                                        .bg(theme.face)
+                                                        .flex()
+                                                        .justify_center()
+                                                        .items_center()
+                                                        .on_mouse_down(
+                                                            MouseButton::Left,
+                                                            cx.listener(|view, _, window, cx| {
+                                                                let d = view.difficulty;
+                                                                view.reset(d, window, cx);
+                                                            }),
+                                                        )
+                                                        .child(match status {
+                                                            GameStatus::Won => "😎",
+                                                            GameStatus::Lost => "😵",
+                                                            _ => "🙂",
                                                         }),
-                                                    )
-                                                    .child(match status {
-                                                        GameStatus::Won => "😎",
-                                                        GameStatus::Lost => "😵",
-                                                        _ => "🙂",
-                                                    }),
-                                            ),
+                                                ),
                                         ),
                                     ),
                                 )
                                 .child(
                                     // Timer
                                     bevel_sunken_thin(
+                                        &theme,
                                         div()
-                                            .text_color(color_red())
+                                            .text_color(theme.counter_text)
                                             .font_weight(FontWeight::BOLD)
                                             .text_size(px(24.0))
                                             .font_family("Courier New")
@@ -330,15 +1101,52 @@ impl Render for MinesweeperView {
                     )
                     .child(
                         // Board
-                        bevel_sunken(div().flex().flex_col().children(grid)),
+                        bevel_sunken(
+                            &theme,
+                            div()
+                                .relative()
+                                .flex()
+                                .flex_col()
+                                .children(grid)
+                                .when(self.game.wrap, |board| {
+                                    board.child(wrap_overlay(theme.wrap_edge))
+                                })
+                                .when_some(self.performance, |board, performance| {
+                                    board.child(self.render_end_panel(performance, cx))
+                                })
+                                .when(self.is_paused(), |board| {
+                                    board.child(self.render_pause_cover(cx))
+                                }),
+                        ),
                     ),
             ))
+            .child(
+                // Status line
+                div()
+                    .h(px(STATUS_LINE_HEIGHT))
+                    .px(px(3.0))
+                    .text_size(px(11.0))
+                    .text_color(theme.text)
+                    .child(status_line),
+            )
+            .when_some(self.playback.as_ref(), |root, playback| {
+                root.child(self.render_replay_controls(playback, cx))
+            })
     }
 }
 
 impl MinesweeperView {
+    fn hint_color(&self, row: u32, col: u32, theme: &Theme) -> Option<Rgba> {
+        match self.hint? {
+            Hint::Safe { row: r, col: c } if (r, c) == (row, col) => Some(theme.hint_safe),
+            Hint::Guess { row: r, col: c, .. } if (r, c) == (row, col) => Some(theme.hint_guess),
+            _ => None,
+        }
+    }
+
     fn render_cell(&self, row: u32, col: u32, cell: &Cell, cx: &Context<Self>) -> Div {
-        let cell_size = px(16.0);
+        let cell_size = px(CELL_SIZE);
+        let theme = theme(cx);
 
         let mut cell_div = div()
             .w(cell_size)
@@ -349,22 +1157,16 @@ impl MinesweeperView {
             .text_size(px(14.0)) // Slightly smaller text for 16px cells
             .font_weight(FontWeight::BOLD);
 
-        if let CellState::Revealed = cell.state {
-            if let CellContent::Number(_) = cell.content {
-                cell_div = cell_div.font_family("Times New Roman"); // Serif for numbers
-            }
+        if let (CellState::Revealed, CellContent::Number(_)) = (cell.state, cell.content) {
+            cell_div = cell_div.font_family("Times New Roman"); // Serif for numbers
         }
 
         // Determine if this cell should be visually pressed (revealed style but empty)
         // This happens if it is targeted by a chord action or is a neighbor of a targeted chord action
         let mut visually_pressed = false;
         if let Some((t_row, t_col)) = self.chord_target {
-            // Check if this cell is a neighbor of the target
-            // We need to calculate neighbors here or assume the view knows.
-            // Since we can't easily call self.game.neighbors() inside render loop efficiently without refactoring,
-            // we will do a quick check.
-            let is_neighbor =
-                (row as i32 - t_row as i32).abs() <= 1 && (col as i32 - t_col as i32).abs() <= 1;
+            // Any topology or rule may be in play, so ask the game
+            let is_neighbor = self.game.neighbors(t_row, t_col).contains(&(row, col));
 
             if is_neighbor
                 && (cell.state == CellState::Hidden || cell.state == CellState::QuestionMark)
@@ -380,39 +1182,52 @@ impl MinesweeperView {
         if visually_pressed {
             // Render as pressed (Revealed style but empty content for now)
             cell_div = cell_div
-                .bg(color_gray())
+                .bg(theme.revealed)
                 .border(px(1.0))
-                .border_color(color_dark_gray());
+                .border_color(theme.shadow);
             // No content for pressed state unless we want to show something?
             // In Win2000, it just looks like an empty revealed cell.
             return cell_div;
         }
 
         match cell.state {
-            CellState::Hidden | CellState::Flagged | CellState::QuestionMark => {
+            CellState::Hidden | CellState::Flagged(_) | CellState::QuestionMark => {
+                let face = match &self.probabilities {
+                    Some(grid) if self.heatmap => grid
+                        .get(row, col)
+                        .map_or(theme.face, |p| heat_color(p, theme.face)),
+                    _ => theme.face,
+                };
+
                 // Manual bevel for cell to keep it efficient and tight
                 cell_div = cell_div
-                    .bg(color_dark_gray()) // Shadow Bottom/Right
+                    .bg(theme.shadow) // Shadow Bottom/Right
                     .pb(px(2.0))
                     .pr(px(2.0))
                     .child(
                         div()
                             .w_full()
                             .h_full()
-                            .bg(color_white()) // Highlight Top/Left
+                            .bg(theme.highlight) // Highlight Top/Left
                             .pt(px(2.0))
                             .pl(px(2.0))
                             .child(
                                 div()
                                     .w_full()
                                     .h_full()
-                                    .bg(color_gray())
+                                    .bg(face)
+                                    .text_color(theme.text)
                                     .flex()
                                     .text_size(px(12.0))
                                     .justify_center()
                                     .items_center()
+                                    .when(cell.state.flags() > 1, |face| face.text_size(px(9.0)))
                                     .child(match cell.state {
-                                        CellState::Flagged => "⛳", // Triangular flag is closer to Windows style
+                                        // Stacked flags in the multi-mine variant show their count
+                                        CellState::Flagged(n) if n > 1 => {
+                                            SharedString::from(format!("{}⛳", n))
+                                        }
+                                        CellState::Flagged(_) => "⛳".into(), // Triangular flag is closer to Windows style
                                         // A unicode flag is the best we can do without custom assets.
                                         // The Win2000 flag is red triangle on black pole.
                                         // "⛳" (Triangular Flag on Post) is usually red.
@@ -420,122 +1235,48 @@ impl MinesweeperView {
                                         // We could draw it with divs but that's complex.
                                         // Let's stick with the unicode but maybe make it smaller or different if possible.
                                         // Actually, let's just keep the unicode for now as drawing a flag with divs is overkill.
-                                        CellState::QuestionMark => "?",
-                                        _ => "",
+                                        CellState::QuestionMark => "?".into(),
+                                        _ => "".into(),
                                     }),
                             ),
                     )
-                    .on_mouse_down(
-                        MouseButton::Left,
-                        cx.listener(move |view, _, _window, cx| {
-                            view.handle_click(row, col, cx);
-                        }),
-                    )
-                    .on_mouse_down(
-                        MouseButton::Right,
-                        cx.listener(move |view, _, _window, cx| {
-                            view.handle_right_click(row, col, cx);
-                        }),
-                    );
+                    .when_some(self.hint_color(row, col, theme), |cell_div, color| {
+                        cell_div.relative().child(hint_overlay(color))
+                    });
+                cell_div = self.cell_mouse_listeners(cell_div, row, col, false, cx);
             }
             CellState::Revealed => {
                 cell_div = cell_div
-                    .bg(color_gray())
+                    .bg(theme.revealed)
                     .border(px(1.0)) // Add faint border to simulate grid lines
-                    .border_color(color_dark_gray());
+                    .border_color(theme.shadow);
 
                 if let CellContent::Number(_) = cell.content {
-                    cell_div = cell_div
-                        .on_mouse_down(
-                            MouseButton::Middle,
-                            cx.listener(move |view, _, _window, cx| {
-                                view.handle_chord_start(row, col, cx);
-                            }),
-                        )
-                        .on_mouse_up(
-                            MouseButton::Middle,
-                            cx.listener(move |view, _, _window, cx| {
-                                view.handle_chord_end(row, col, cx);
-                            }),
-                        )
-                        .on_mouse_down(
-                            MouseButton::Left,
-                            cx.listener(move |view, event: &MouseDownEvent, _window, cx| {
-                                view.left_mouse_down = true;
-                                if event.click_count == 2 {
-                                    view.handle_chord_start(row, col, cx);
-                                } else if view.right_mouse_down {
-                                    view.handle_chord_start(row, col, cx);
-                                }
-                            }),
-                        )
-                        .on_mouse_down(
-                            MouseButton::Right,
-                            cx.listener(move |view, _, _window, cx| {
-                                view.right_mouse_down = true;
-                                if view.left_mouse_down {
-                                    view.handle_chord_start(row, col, cx);
-                                }
-                            }),
-                        )
-                        .on_mouse_up(
-                            MouseButton::Left,
-                            cx.listener(move |view, _, _window, cx| {
-                                view.left_mouse_down = false;
-                                // If we were chording, finish it.
-                                view.handle_chord_end(row, col, cx);
-                            }),
-                        )
-                        .on_mouse_up(
-                            MouseButton::Right,
-                            cx.listener(move |view, _, _window, cx| {
-                                view.right_mouse_down = false;
-                                // If we were chording, finish it.
-                                view.handle_chord_end(row, col, cx);
-                            }),
-                        );
+                    cell_div = self.cell_mouse_listeners(cell_div, row, col, true, cx);
                 }
 
-                let content = match cell.content {
-                    CellContent::Empty => "",
-                    CellContent::Mine => {
-                        if cell.exploded {
-                            "💥"
-                        } else {
-                            "💣"
-                        }
-                    }
-                    CellContent::Number(n) => match n {
-                        1 => "1",
-                        2 => "2",
-                        3 => "3",
-                        4 => "4",
-                        5 => "5",
-                        6 => "6",
-                        7 => "7",
-                        8 => "8",
-                        _ => "",
-                    },
+                let content: SharedString = match cell.content {
+                    CellContent::Empty => "".into(),
+                    CellContent::Mine(_) if cell.exploded => "💥".into(),
+                    CellContent::Mine(n) if n > 1 => format!("{}💣", n).into(),
+                    CellContent::Mine(_) => "💣".into(),
+                    CellContent::Number(n) => n.to_string().into(),
                 };
 
                 let color = match cell.content {
-                    CellContent::Number(n) => match n {
-                        1 => rgba(0x0000FFFF), // Blue
-                        2 => rgba(0x008000FF), // Green
-                        3 => rgba(0xFF0000FF), // Red
-                        4 => rgba(0x000080FF), // Dark Blue
-                        5 => rgba(0x800000FF), // Maroon
-                        6 => rgba(0x008080FF), // Teal
-                        7 => rgba(0x000000FF), // Black
-                        8 => rgba(0x808080FF), // Gray
-                        _ => color_black(),
-                    },
-                    _ => color_black(),
+                    CellContent::Number(n) => theme.number(n),
+                    _ => theme.text,
                 };
 
                 let mut inner = cell_div.text_color(color);
-                if cell.content == CellContent::Mine && cell.exploded {
-                    inner = inner.bg(color_red());
+                if matches!(cell.content, CellContent::Number(n) if n >= 10)
+                    || cell.content.mines() > 1
+                {
+                    // Two characters have to fit the same 16px cell
+                    inner = inner.text_size(px(10.0));
+                }
+                if cell.content.is_mine() && cell.exploded {
+                    inner = inner.bg(theme.exploded);
                 }
                 cell_div = inner.child(content);
             }
@@ -545,25 +1286,115 @@ impl MinesweeperView {
     }
 }
 
-fn main() {
-    Application::new().run(|cx| {
-        cx.set_menus(vec![Menu {
+// Menus can't show check marks, so toggles are rebuilt with a tick in their label
+fn checked(label: &str, on: bool) -> String {
+    if on {
+        format!("✓ {}", label)
+    } else {
+        label.to_string()
+    }
+}
+
+fn app_menus(view: &MinesweeperView) -> Vec<Menu> {
+    vec![
+        Menu {
             name: "Game".into(),
             items: vec![
                 MenuItem::action("New", NewGame),
+                MenuItem::action("Pause/Resume", Pause),
                 MenuItem::separator(),
                 MenuItem::action("Beginner", DiffBeginner),
                 MenuItem::action("Intermediate", DiffIntermediate),
                 MenuItem::action("Expert", DiffExpert),
+                MenuItem::action("Custom...", DiffCustom),
+                MenuItem::separator(),
+                MenuItem::action("Open Game...", OpenGame),
+                MenuItem::action("Save Game...", SaveGame),
+                MenuItem::action("Statistics...", ShowStatistics),
+                MenuItem::separator(),
+                MenuItem::action("Game from Seed...", GameFromSeed),
+                MenuItem::action("Hint", ShowHint),
+                MenuItem::action(checked("No-Guess Mode", view.no_guess), ToggleNoGuess),
+                MenuItem::action(checked("Hex Grid", view.grid == Grid::Hex), ToggleHexGrid),
+                MenuItem::action(checked("Wrap-Around Edges", view.wrap), ToggleWrap),
+                MenuItem::action(
+                    checked("Multi-Mine Cells", view.multi_mine),
+                    ToggleMultiMine,
+                ),
+                MenuItem::submenu(Menu {
+                    name: "Neighbours".into(),
+                    items: vec![
+                        MenuItem::action(
+                            checked("Classic (8)", view.rule == Neighborhood::Classic),
+                            RuleClassic,
+                        ),
+                        MenuItem::action(
+                            checked("Cross (4)", view.rule == Neighborhood::Cross),
+                            RuleCross,
+                        ),
+                        MenuItem::action(
+                            checked("Knight (8)", view.rule == Neighborhood::Knight),
+                            RuleKnight,
+                        ),
+                        MenuItem::action(
+                            checked("Radius 2 (24)", view.rule == Neighborhood::Radius2),
+                            RuleRadius2,
+                        ),
+                    ],
+                }),
+                MenuItem::action(checked("Probability Heatmap", view.heatmap), ToggleHeatmap),
+                MenuItem::action(checked("Timer Tenths", view.tenths), ToggleTenths),
                 MenuItem::separator(),
                 MenuItem::action("Exit", Exit),
             ],
-        }]);
+        },
+        Menu {
+            name: "Edit".into(),
+            items: vec![
+                MenuItem::action("Undo", Undo),
+                MenuItem::action("Redo", Redo),
+                MenuItem::separator(),
+                MenuItem::action("Copy Board", CopyBoard),
+                MenuItem::action("Paste Board", PasteBoard),
+            ],
+        },
+        Menu {
+            name: "Replay".into(),
+            items: vec![
+                MenuItem::action("Open Replay...", OpenReplay),
+                MenuItem::separator(),
+                MenuItem::action("Play/Pause", ReplayPlayPause),
+                MenuItem::action("Step Back", ReplayStepBack),
+                MenuItem::action("Step Forward", ReplayStepForward),
+                MenuItem::action("Slower", ReplaySlower),
+                MenuItem::action("Faster", ReplayFaster),
+            ],
+        },
+        Menu {
+            name: "Theme".into(),
+            items: ThemeName::ALL
+                .into_iter()
+                .map(|name| {
+                    let label = checked(name.label(), view.settings.theme == name);
+                    match name {
+                        ThemeName::Win2000 => MenuItem::action(label, ThemeWin2000),
+                        ThemeName::Xp => MenuItem::action(label, ThemeXp),
+                        ThemeName::Dark => MenuItem::action(label, ThemeDark),
+                        ThemeName::HighContrast => MenuItem::action(label, ThemeHighContrast),
+                    }
+                })
+                .collect(),
+        },
+    ]
+}
 
+fn main() {
+    Application::new().run(|cx| {
+        let (rows, cols, _) = Difficulty::Beginner.config();
         let options = WindowOptions {
             window_bounds: Some(WindowBounds::Windowed(Bounds::centered(
                 None,
-                size(px(180.0), px(240.0)),
+                board_window_size(rows, cols, Grid::Square),
                 cx,
             ))),
             titlebar: Some(TitlebarOptions {
@@ -573,14 +1404,23 @@ fn main() {
             }),
             ..Default::default()
         };
-        let _ = cx.open_window(options, |_, cx| {
-            // Quit the app when a window is closed
-            cx.on_window_closed(|cx| {
-                cx.quit();
+
+        if let Ok(main_window) = cx.open_window(options, |window, cx| {
+            cx.new(|cx| MinesweeperView::new(window, cx))
+        }) {
+            main_window
+                .update(cx, |view, window, cx| view.offer_resume(window, cx))
+                .ok();
+
+            // Quit the app when the game window is closed; dialogs may come and go
+            let main_id = main_window.window_id();
+            cx.on_window_closed(move |cx| {
+                if !cx.windows().iter().any(|w| w.window_id() == main_id) {
+                    cx.quit();
+                }
             })
             .detach();
-            cx.new(|cx| MinesweeperView::new(cx))
-        });
+        }
 
         cx.activate(true); // Bring to front
     });
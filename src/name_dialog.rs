@@ -0,0 +1,85 @@
+use gpui::*;
+
+use crate::text_field::TextField;
+use crate::theme::theme;
+use crate::{MinesweeperView, bevel_button};
+
+const MAX_NAME_LEN: usize = 24;
+
+// "Fastest Mine Sweepers" name prompt, shown when a win makes the best times
+pub struct NameDialog {
+    name: Entity<TextField>,
+    message: SharedString,
+    index: usize, // Statistics entry being named
+    game_view: WeakEntity<MinesweeperView>,
+}
+
+impl NameDialog {
+    pub fn new(
+        message: SharedString,
+        current: &str,
+        index: usize,
+        game_view: WeakEntity<MinesweeperView>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let name = cx.new(|cx| {
+            TextField::new(current, MAX_NAME_LEN, |c| !c.is_control(), cx).with_width(px(200.0))
+        });
+        name.read(cx).focus_handle(cx).focus(window);
+        Self {
+            name,
+            message,
+            index,
+            game_view,
+        }
+    }
+
+    fn confirm(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let name = self.name.read(cx).text().trim().to_string();
+        let index = self.index;
+        self.game_view
+            .update(cx, |view, cx| view.set_record_name(index, &name, cx))
+            .ok();
+        window.remove_window();
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event.keystroke.key.as_str() {
+            "enter" => self.confirm(window, cx),
+            "escape" => window.remove_window(),
+            _ => {}
+        }
+    }
+}
+
+impl Render for NameDialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = theme(cx).clone();
+        div()
+            .on_key_down(cx.listener(Self::handle_key_down))
+            .size_full()
+            .bg(theme.face)
+            .text_color(theme.text)
+            .p(px(10.0))
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .child(div().text_size(px(12.0)).child(self.message.clone()))
+            .child(self.name.clone())
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .child(bevel_button(&theme, "OK").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _, window, cx| view.confirm(window, cx)),
+                    )),
+            )
+    }
+}
@@ -0,0 +1,239 @@
+use gpui::*;
+use std::time::{Duration, Instant};
+
+use crate::theme::theme;
+use crate::{MinesweeperView, bevel_button_sized, board_window_size, show_error};
+use minesweep::replay::{Player, Replay};
+
+pub const SPEEDS: [f32; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+
+pub const CONTROL_BAR_HEIGHT: f32 = 30.0;
+
+// How often the board is brought up to date while playing
+const FRAME: Duration = Duration::from_millis(30);
+
+// Replay mode of the game window: the board follows a recording instead of the mouse
+pub struct Playback {
+    pub player: Player,
+    pub speed: usize, // Index into SPEEDS
+    // Wall clock and replay time when play was last pressed; None while paused
+    anchor: Option<(Instant, u64)>,
+    task: Option<Task<()>>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            player: Player::new(replay),
+            speed: SPEEDS.iter().position(|&s| s == 1.0).unwrap_or(0),
+            anchor: None,
+            task: None,
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    fn target_ms(&self) -> Option<u64> {
+        let (started, from_ms) = self.anchor?;
+        let played = started.elapsed().as_secs_f64() * 1000.0 * SPEEDS[self.speed] as f64;
+        Some(from_ms + played as u64)
+    }
+
+    // Restarts the wall clock from the current position, e.g. after a speed change
+    fn reanchor(&mut self) {
+        if self.anchor.is_some() {
+            self.anchor = Some((Instant::now(), self.player.time_ms()));
+        }
+    }
+}
+
+impl MinesweeperView {
+    pub fn open_replay(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Open Replay".into()),
+        });
+        cx.spawn_in(window, async move |view, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            view.update_in(cx, |view, window, cx| match Replay::load(&path) {
+                Ok(replay) => view.start_replay(replay, window, cx),
+                Err(err) => show_error(window, "Could not open replay", &err.to_string(), cx),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub fn start_replay(&mut self, replay: Replay, window: &mut Window, cx: &mut Context<Self>) {
+        let old_size = self.window_size();
+        self.difficulty = replay.difficulty();
+        self.game = replay.new_game();
+        self.replay_path = None;
+        self.stats_entry = None;
+        self.board_stats = None;
+        self.generating = None;
+        self.playback = Some(Playback::new(replay));
+        self.board_changed(cx);
+        self.fit_window(old_size, window);
+        self.toggle_playing(cx);
+    }
+
+    pub fn window_size(&self) -> Size<Pixels> {
+        let mut size = board_window_size(self.game.rows, self.game.cols, self.game.grid);
+        if self.playback.is_some() {
+            size.height += px(CONTROL_BAR_HEIGHT);
+        }
+        size
+    }
+
+    pub fn toggle_playing(&mut self, cx: &mut Context<Self>) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        if playback.is_playing() {
+            playback.anchor = None;
+            playback.task = None;
+            cx.notify();
+            return;
+        }
+        if playback.player.is_finished() {
+            // Play again from the start
+            playback.player.rewind(&mut self.game);
+            self.board_changed(cx);
+        }
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        playback.anchor = Some((Instant::now(), playback.player.time_ms()));
+        playback.task = Some(cx.spawn(async move |view, cx| {
+            loop {
+                cx.background_executor().timer(FRAME).await;
+                let playing = view.update(cx, |view, cx| view.tick(cx)).unwrap_or(false);
+                if !playing {
+                    break;
+                }
+            }
+        }));
+        cx.notify();
+    }
+
+    // One frame of playback; false once paused or at the end
+    fn tick(&mut self, cx: &mut Context<Self>) -> bool {
+        let Some(playback) = &mut self.playback else {
+            return false;
+        };
+        let Some(target) = playback.target_ms() else {
+            return false;
+        };
+        let tenths = self.game.elapsed_ms() / 100;
+        let changed = playback.player.advance_to(&mut self.game, target);
+        let finished = playback.player.is_finished();
+        if finished {
+            playback.anchor = None;
+        }
+        if changed {
+            self.board_changed(cx);
+        } else if finished || tenths != self.game.elapsed_ms() / 100 {
+            cx.notify();
+        }
+        !finished
+    }
+
+    pub fn step_replay(&mut self, forward: bool, cx: &mut Context<Self>) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        // Stepping takes over from the clock
+        playback.anchor = None;
+        playback.task = None;
+        let changed = if forward {
+            playback.player.step_forward(&mut self.game)
+        } else {
+            playback.player.step_back(&mut self.game)
+        };
+        if changed {
+            self.board_changed(cx);
+        } else {
+            cx.notify();
+        }
+    }
+
+    pub fn change_speed(&mut self, faster: bool, cx: &mut Context<Self>) {
+        let Some(playback) = &mut self.playback else {
+            return;
+        };
+        playback.reanchor();
+        playback.speed = if faster {
+            (playback.speed + 1).min(SPEEDS.len() - 1)
+        } else {
+            playback.speed.saturating_sub(1)
+        };
+        cx.notify();
+    }
+
+    pub fn replay_status_text(&self, playback: &Playback) -> String {
+        let player = &playback.player;
+        let mut text = format!(
+            "Replay · move {}/{} · {}x",
+            player.position(),
+            player.len(),
+            SPEEDS[playback.speed]
+        );
+        if !playback.is_playing() {
+            text.push_str(if player.is_finished() {
+                " · ended"
+            } else {
+                " · paused"
+            });
+        }
+        text
+    }
+
+    pub fn render_replay_controls(&self, playback: &Playback, cx: &mut Context<Self>) -> Div {
+        let theme = theme(cx).clone();
+        let button = |label: &'static str| bevel_button_sized(&theme, label, px(24.0));
+        div()
+            .h(px(CONTROL_BAR_HEIGHT))
+            .flex()
+            .items_center()
+            .justify_center()
+            .gap(px(4.0))
+            .text_color(theme.text)
+            .child(button("|◀").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.step_replay(false, cx)),
+            ))
+            .child(
+                button(if playback.is_playing() {
+                    "❚❚"
+                } else {
+                    "▶"
+                })
+                .on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _, _, cx| view.toggle_playing(cx)),
+                ),
+            )
+            .child(button("▶|").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.step_replay(true, cx)),
+            ))
+            .child(button("−").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.change_speed(false, cx)),
+            ))
+            .child(button("+").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.change_speed(true, cx)),
+            ))
+    }
+}
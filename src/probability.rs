@@ -0,0 +1,238 @@
+use crate::game::Minesweeper;
+use crate::solver::{self, Board, DEFAULT_ENUMERATION_LIMIT, Visible};
+
+/// Mine probability of every unknown cell, indexed like `Minesweeper::cells`.
+/// Revealed and flagged cells have no entry.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProbabilityGrid {
+    pub rows: u32,
+    pub cols: u32,
+    pub cells: Vec<Option<f64>>,
+}
+
+impl ProbabilityGrid {
+    pub fn get(&self, row: u32, col: u32) -> Option<f64> {
+        self.cells[(row * self.cols + col) as usize]
+    }
+
+    /// The unknown cell least likely to be a mine; ties go to the first in row-major order.
+    pub fn safest(&self) -> Option<((u32, u32), f64)> {
+        let mut best: Option<(usize, f64)> = None;
+        for (idx, p) in self.cells.iter().enumerate() {
+            if let Some(p) = *p
+                && best.is_none_or(|(_, b)| p < b)
+            {
+                best = Some((idx, p));
+            }
+        }
+        best.map(|(idx, p)| ((idx as u32 / self.cols, idx as u32 % self.cols), p))
+    }
+}
+
+/// Probabilities for the visible state of `game`.
+pub fn probabilities(game: &Minesweeper) -> ProbabilityGrid {
+    mine_probabilities(&Board::from_game(game))
+}
+
+/// Exact mine probabilities given the revealed numbers, flags and the total
+/// mine count.
+///
+/// Every consistent assignment of the frontier (unknown cells next to a
+/// number) is weighted by the ways the leftover mines fit into the cells no
+/// number touches, so interior cells and the global count are both accounted
+/// for. A frontier group too large to enumerate falls back to the local
+/// density of its numbers and is treated like the interior for weighting.
+pub fn mine_probabilities(board: &Board) -> ProbabilityGrid {
+    let constraints = solver::constraints(board);
+    let frontier = solver::enumerate(board, &constraints, DEFAULT_ENUMERATION_LIMIT);
+    let mut cells: Vec<Option<f64>> = vec![None; board.cells.len()];
+
+    // Local density: the worst need/unknown ratio of any number touching the cell
+    let mut local = vec![0.0f64; board.cells.len()];
+    for constraint in &constraints {
+        let density = constraint.need as f64 / constraint.cells.len() as f64;
+        for &cell in &constraint.cells {
+            local[cell] = local[cell].max(density);
+        }
+    }
+
+    // Free cells: the interior plus any frontier group we could not enumerate
+    let mut free = frontier.interior.clone();
+    let mut groups = Vec::new();
+    for component in &frontier.components {
+        match &component.tally {
+            Some(tally) if tally.solutions.iter().any(|&n| n > 0.0) => {
+                // Scale each group to a max of 1; constant factors cancel out
+                let max = tally.solutions.iter().copied().fold(0.0, f64::max);
+                let solutions: Vec<f64> = tally.solutions.iter().map(|n| n / max).collect();
+                let mine_counts: Vec<Vec<f64>> = tally
+                    .mine_counts
+                    .iter()
+                    .map(|row| row.iter().map(|n| n / max).collect())
+                    .collect();
+                groups.push((&component.cells, solutions, mine_counts));
+            }
+            _ => {
+                for &cell in &component.cells {
+                    cells[cell] = Some(local[cell]);
+                }
+                free.extend(&component.cells);
+            }
+        }
+    }
+
+    let remaining = frontier.remaining;
+    let weights = binomial_weights(free.len(), remaining);
+
+    // Ways to reach each mine total with the enumerated groups
+    let convolve = |acc: &Vec<f64>, solutions: &Vec<f64>| {
+        let mut next = vec![0.0; remaining + 1];
+        for (s, &a) in acc.iter().enumerate().filter(|&(_, &a)| a > 0.0) {
+            for (k, &n) in solutions.iter().enumerate().filter(|&(_, &n)| n > 0.0) {
+                if s + k <= remaining {
+                    next[s + k] += a * n;
+                }
+            }
+        }
+        next
+    };
+    let mut unit = vec![0.0; remaining + 1];
+    unit[0] = 1.0;
+    let mut prefix = vec![unit.clone()];
+    for (_, solutions, _) in &groups {
+        let next = convolve(prefix.last().unwrap(), solutions);
+        prefix.push(next);
+    }
+    let mut suffix = vec![unit];
+    for (_, solutions, _) in groups.iter().rev() {
+        let next = convolve(suffix.last().unwrap(), solutions);
+        suffix.push(next);
+    }
+    suffix.reverse();
+
+    let all = &prefix[groups.len()];
+    let total: f64 = (0..=remaining)
+        .map(|s| all[s] * weights[remaining - s])
+        .sum();
+    if total <= 0.0 {
+        // Contradictory flags: no layout fits, so only local densities make sense
+        for (idx, cell) in board.cells.iter().enumerate() {
+            if *cell == Visible::Unknown {
+                cells[idx] = Some(local[idx]);
+            }
+        }
+        return ProbabilityGrid {
+            rows: board.rows,
+            cols: board.cols,
+            cells,
+        };
+    }
+
+    for (i, (group_cells, solutions, mine_counts)) in groups.iter().enumerate() {
+        // Weight of this group holding k mines, over everything else
+        let others = convolve(&prefix[i], &suffix[i + 1]);
+        let outside: Vec<f64> = (0..solutions.len())
+            .map(|k| match remaining.checked_sub(k) {
+                Some(left) => (0..=left).map(|s| others[s] * weights[left - s]).sum(),
+                None => 0.0,
+            })
+            .collect();
+        for (j, &cell) in group_cells.iter().enumerate() {
+            let mined: f64 = (0..solutions.len())
+                .filter(|&k| k <= remaining)
+                .map(|k| mine_counts[k][j] * outside[k])
+                .sum();
+            cells[cell] = Some((mined / total).clamp(0.0, 1.0));
+        }
+    }
+
+    if !free.is_empty() {
+        // Expected share of the leftover mines per free cell
+        let expected: f64 = (0..=remaining)
+            .map(|s| all[s] * weights[remaining - s] * (remaining - s) as f64)
+            .sum::<f64>()
+            / total
+            / free.len() as f64;
+        for &cell in &frontier.interior {
+            cells[cell] = Some(expected.clamp(0.0, 1.0));
+        }
+    }
+
+    ProbabilityGrid {
+        rows: board.rows,
+        cols: board.cols,
+        cells,
+    }
+}
+
+// weights[m] is proportional to C(free, m): the ways to put m mines into the
+// free cells. Worked out in log space and scaled so the largest is 1, since
+// the raw values overflow f64 on big boards.
+fn binomial_weights(free: usize, max: usize) -> Vec<f64> {
+    let mut ln_fact = vec![0.0f64; free + 1];
+    for n in 1..=free {
+        ln_fact[n] = ln_fact[n - 1] + (n as f64).ln();
+    }
+    let ln_choose: Vec<Option<f64>> = (0..=max)
+        .map(|m| (m <= free).then(|| ln_fact[free] - ln_fact[m] - ln_fact[free - m]))
+        .collect();
+    let top = ln_choose
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f64::NEG_INFINITY, f64::max);
+    ln_choose
+        .iter()
+        .map(|ln| ln.map_or(0.0, |ln| (ln - top).exp()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_probabilities(grid: &ProbabilityGrid, expected: &[Option<f64>]) {
+        assert_eq!(grid.cells.len(), expected.len());
+        for (idx, (&p, &e)) in grid.cells.iter().zip(expected).enumerate() {
+            match (p, e) {
+                (Some(p), Some(e)) => assert!((p - e).abs() < 1e-9, "cell {}: {} != {}", idx, p, e),
+                _ => assert_eq!(p, e, "cell {}", idx),
+            }
+        }
+    }
+
+    #[test]
+    fn one_number_splits_its_mine() {
+        // One mine beside the 1 either way, and one among the three far cells
+        let grid = mine_probabilities(&Board::parse(2, &["#1####"]));
+        let third = Some(1.0 / 3.0);
+        assert_probabilities(&grid, &[Some(0.5), None, Some(0.5), third, third, third]);
+    }
+
+    #[test]
+    fn layouts_are_weighted_by_the_ways_to_fill_the_interior() {
+        // A mine in the middle leaves one for the two far cells, two ways;
+        // mines at both ends leave none, one way
+        let grid = mine_probabilities(&Board::parse(2, &["#1#1###"]));
+        let (third, two_thirds) = (Some(1.0 / 3.0), Some(2.0 / 3.0));
+        assert_probabilities(&grid, &[third, None, two_thirds, None, third, third, third]);
+        assert_eq!(grid.safest(), Some(((0, 0), 1.0 / 3.0)));
+    }
+
+    #[test]
+    fn mine_count_can_decide_the_frontier() {
+        let grid = mine_probabilities(&Board::parse(1, &["#1#1#"]));
+        assert_probabilities(&grid, &[Some(0.0), None, Some(1.0), None, Some(0.0)]);
+        let grid = mine_probabilities(&Board::parse(2, &["#1#1#"]));
+        assert_probabilities(&grid, &[Some(1.0), None, Some(0.0), None, Some(1.0)]);
+    }
+
+    #[test]
+    fn flagged_cells_count_toward_the_total() {
+        // The flag is the 1's mine, which clears its other neighbours and
+        // leaves one mine for the two cells on the right
+        let grid = mine_probabilities(&Board::parse(2, &["F1##", "####"]));
+        let (clear, half) = (Some(0.0), Some(0.5));
+        assert_probabilities(&grid, &[None, None, clear, half, clear, clear, clear, half]);
+    }
+}
@@ -0,0 +1,496 @@
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::game::{ConfigError, Difficulty, GameStatus, MAX_CELL_MINES, Minesweeper};
+use crate::topology::{Grid, Neighborhood};
+use crate::{FileError, FileFormat};
+
+/// Format version written to every replay. Bump it whenever the layout of
+/// `Replay` changes in a way older readers would misread.
+pub const REPLAY_VERSION: u32 = 1;
+
+const FORMAT: FileFormat = FileFormat::new("replay", REPLAY_VERSION);
+
+/// One player move. Coordinates are zero-based.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    Reveal { row: u32, col: u32 },
+    Flag { row: u32, col: u32 },
+    Chord { row: u32, col: u32 },
+    Undo,
+    Redo,
+}
+
+impl Action {
+    /// Plays this move on `game`, exactly as the player did. Moves the game
+    /// refuses are skipped, as they were when recorded.
+    pub fn apply(self, game: &mut Minesweeper) {
+        match self {
+            Action::Reveal { row, col } => {
+                game.reveal(row, col).ok();
+            }
+            Action::Flag { row, col } => {
+                game.toggle_flag(row, col).ok();
+            }
+            Action::Chord { row, col } => {
+                game.chord(row, col).ok();
+            }
+            Action::Undo => {
+                game.undo();
+            }
+            Action::Redo => {
+                game.redo();
+            }
+        }
+    }
+
+    fn cell(self) -> Option<(u32, u32)> {
+        match self {
+            Action::Reveal { row, col }
+            | Action::Flag { row, col }
+            | Action::Chord { row, col } => Some((row, col)),
+            Action::Undo | Action::Redo => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Event {
+    // Playing time on the game clock when the move was made
+    pub at_ms: u64,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+// Collects the moves of one game as they are played. Saved along with the
+// game, whose clock carries on from where it stopped, so times keep rising.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Recorder {
+    events: Vec<Event>,
+    // Began on a board already under way, such as a pasted one
+    #[serde(default)]
+    midway: bool,
+}
+
+impl Recorder {
+    pub fn push(&mut self, action: Action, at_ms: u64) {
+        self.events.push(Event { at_ms, action });
+    }
+
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    pub fn start_midway(&mut self) {
+        self.midway = true;
+    }
+
+    pub fn is_midway(&self) -> bool {
+        self.midway
+    }
+}
+
+/// A finished (or abandoned) game: the mine layout plus every click, wasted
+/// ones included, enough to play it back exactly.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Replay {
+    #[serde(skip_serializing)] // Written by `FORMAT` beside the rest
+    pub version: u32,
+    // RFC 3339, local time
+    pub recorded_at: String,
+    pub rows: u32,
+    pub cols: u32,
+    pub mines: u32,
+    pub seed: u64,
+    #[serde(default)]
+    pub grid: Grid,
+    #[serde(default)]
+    pub wrap: bool,
+    #[serde(default)]
+    pub rule: Neighborhood,
+    #[serde(default)]
+    pub multi_mine: bool,
+    pub no_guess: bool,
+    pub guess_free: bool,
+    // Mine cell indices, row-major; a cell with several mines is repeated
+    pub layout: Vec<usize>,
+    pub won: bool,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    File(FileError),
+    Config(ConfigError),
+    BadLayout,
+    CellOutOfRange { event: usize },
+    OutOfOrder { event: usize },
+    OutcomeMismatch,
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::File(err) => write!(f, "{}", err),
+            ReplayError::Config(err) => write!(f, "Invalid board: {}", err),
+            ReplayError::BadLayout => write!(f, "Mine layout does not match the board"),
+            ReplayError::CellOutOfRange { event } => {
+                write!(f, "Move {} is outside the board", event + 1)
+            }
+            ReplayError::OutOfOrder { event } => {
+                write!(
+                    f,
+                    "Move {} is timestamped before the one preceding it",
+                    event + 1
+                )
+            }
+            ReplayError::OutcomeMismatch => {
+                write!(f, "Playing the moves does not give the recorded result")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReplayError::File(err) => err.source(),
+            ReplayError::Config(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<FileError> for ReplayError {
+    fn from(err: FileError) -> Self {
+        ReplayError::File(err)
+    }
+}
+
+/// Where finished games are saved, e.g. `~/.local/share/minesweep/replays`.
+pub fn replays_dir() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("replays"))
+}
+
+impl Replay {
+    /// Captures `game` as it stands. `None` until the first click has placed
+    /// mines, and for games whose moves weren't all recorded from a fresh
+    /// board, since playing them back would end somewhere else.
+    pub fn from_game(game: &Minesweeper) -> Option<Self> {
+        let layout = game.mine_layout();
+        if layout.is_empty() || !game.recorded_from_start() {
+            return None;
+        }
+        Some(Self {
+            version: REPLAY_VERSION,
+            recorded_at: Local::now().to_rfc3339(),
+            rows: game.rows,
+            cols: game.cols,
+            mines: game.mines,
+            seed: game.seed,
+            grid: game.grid,
+            wrap: game.wrap,
+            rule: game.rule,
+            multi_mine: game.multi_mine,
+            no_guess: game.no_guess,
+            guess_free: game.guess_free,
+            layout,
+            won: game.status == GameStatus::Won,
+            events: game.recording().to_vec(),
+        })
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::from_config(self.rows, self.cols, self.mines)
+    }
+
+    /// A fresh game on the recorded layout, ready for the first event.
+    pub fn new_game(&self) -> Minesweeper {
+        let mut game = Minesweeper::with_seed(self.difficulty(), self.seed);
+        game.grid = self.grid;
+        game.wrap = self.wrap;
+        game.rule = self.rule;
+        game.multi_mine = self.multi_mine;
+        game.no_guess = self.no_guess;
+        game.guess_free = self.guess_free;
+        game.set_layout(&self.layout);
+        game
+    }
+
+    /// The game after every recorded move.
+    pub fn play(&self) -> Minesweeper {
+        let mut game = self.new_game();
+        for event in &self.events {
+            event.action.apply(&mut game);
+        }
+        game
+    }
+
+    /// Checks that the replay can be played back: the board and layout are
+    /// valid, every move is on the board and in time order, and the moves
+    /// lead to the recorded result.
+    pub fn validate(&self) -> Result<(), ReplayError> {
+        if self.version > REPLAY_VERSION {
+            return Err(ReplayError::File(FileError::UnsupportedVersion {
+                name: FORMAT.name,
+                found: self.version,
+                supported: REPLAY_VERSION,
+            }));
+        }
+        self.difficulty().validate().map_err(ReplayError::Config)?;
+
+        let cells = (self.rows * self.cols) as usize;
+        let per_cell = if self.multi_mine { MAX_CELL_MINES } else { 1 };
+        let mut seen = vec![0; cells];
+        if self.layout.len() != self.mines as usize {
+            return Err(ReplayError::BadLayout);
+        }
+        for &idx in &self.layout {
+            if idx >= cells || seen[idx] == per_cell {
+                return Err(ReplayError::BadLayout);
+            }
+            seen[idx] += 1;
+        }
+
+        let mut last_ms = 0;
+        for (i, event) in self.events.iter().enumerate() {
+            if let Some((row, col)) = event.action.cell()
+                && (row >= self.rows || col >= self.cols)
+            {
+                return Err(ReplayError::CellOutOfRange { event: i });
+            }
+            if event.at_ms < last_ms {
+                return Err(ReplayError::OutOfOrder { event: i });
+            }
+            last_ms = event.at_ms;
+        }
+
+        if (self.play().status == GameStatus::Won) != self.won {
+            return Err(ReplayError::OutcomeMismatch);
+        }
+        Ok(())
+    }
+
+    pub fn to_json(&self) -> String {
+        FORMAT.to_json(self)
+    }
+
+    /// Parses and validates a replay.
+    pub fn from_json(json: &str) -> Result<Self, ReplayError> {
+        let replay: Replay = FORMAT.from_json(json)?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    /// Reads and validates a replay file.
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let replay: Replay = FORMAT.load(path)?;
+        replay.validate()?;
+        Ok(replay)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        Ok(FORMAT.save(path, self)?)
+    }
+
+    /// File name to save under: the recording time plus the board, so a
+    /// directory listing sorts by date.
+    pub fn file_name(&self) -> String {
+        let stamp = DateTime::parse_from_rfc3339(&self.recorded_at)
+            .map(|time| time.format("%Y-%m-%d_%H-%M-%S").to_string())
+            .unwrap_or_else(|_| "replay".to_string());
+        format!("{}_{}x{}_{}.json", stamp, self.cols, self.rows, self.seed)
+    }
+}
+
+/// Plays a replay back onto a game built with `Replay::new_game`, following
+/// the recorded timing. The clock is in replay milliseconds, so callers
+/// control the speed by how fast they advance it.
+pub struct Player {
+    replay: Replay,
+    position: usize,
+    time_ms: u64,
+}
+
+impl Player {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            position: 0,
+            time_ms: 0,
+        }
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// Number of events applied so far.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    pub fn len(&self) -> usize {
+        self.replay.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.events.is_empty()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position == self.len()
+    }
+
+    pub fn time_ms(&self) -> u64 {
+        self.time_ms
+    }
+
+    /// Moves the clock forward to `time_ms`, applying every event due by then.
+    /// The clock stops at the last event. Returns whether the board changed.
+    pub fn advance_to(&mut self, game: &mut Minesweeper, time_ms: u64) -> bool {
+        let start = self.position;
+        self.time_ms = self.time_ms.max(time_ms);
+        while let Some(event) = self.replay.events.get(self.position) {
+            if event.at_ms > self.time_ms {
+                break;
+            }
+            event.action.apply(game);
+            self.position += 1;
+        }
+        if self.is_finished() {
+            self.time_ms = self.replay.events.last().map_or(0, |event| event.at_ms);
+        }
+        self.sync_timer(game);
+        self.position != start
+    }
+
+    /// Applies the next event and jumps the clock to it.
+    pub fn step_forward(&mut self, game: &mut Minesweeper) -> bool {
+        let Some(event) = self.replay.events.get(self.position) else {
+            return false;
+        };
+        event.action.apply(game);
+        self.position += 1;
+        self.time_ms = event.at_ms;
+        self.sync_timer(game);
+        true
+    }
+
+    /// Takes back the last applied event by playing the replay again up to
+    /// the one before it.
+    pub fn step_back(&mut self, game: &mut Minesweeper) -> bool {
+        if self.position == 0 {
+            return false;
+        }
+        let target = self.position - 1;
+        *game = self.replay.new_game();
+        for event in &self.replay.events[..target] {
+            event.action.apply(game);
+        }
+        self.position = target;
+        self.time_ms = target
+            .checked_sub(1)
+            .map_or(0, |last| self.replay.events[last].at_ms);
+        self.sync_timer(game);
+        true
+    }
+
+    /// Goes back to before the first event, on a fresh board.
+    pub fn rewind(&mut self, game: &mut Minesweeper) {
+        *game = self.replay.new_game();
+        self.position = 0;
+        self.time_ms = 0;
+        self.sync_timer(game);
+    }
+
+    // The LCD shows replay time rather than the playback game's own clock
+    fn sync_timer(&self, game: &mut Minesweeper) {
+        game.set_elapsed(Duration::from_millis(self.time_ms));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // M 1 1 M, won in two reveals with a wasted flag between them
+    fn won_replay() -> Replay {
+        let mut game = Minesweeper::new(Difficulty::custom(1, 4, 2).unwrap());
+        game.set_layout(&[0, 3]);
+        game.reveal(0, 1).unwrap();
+        game.toggle_flag(0, 1).unwrap();
+        game.reveal(0, 2).unwrap();
+        assert_eq!(game.status, GameStatus::Won);
+        Replay::from_game(&game).unwrap()
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let replay = won_replay();
+        let loaded = Replay::from_json(&replay.to_json()).unwrap();
+        assert_eq!(loaded, replay);
+        assert_eq!(loaded.events.len(), 3);
+        assert_eq!(loaded.play().status, GameStatus::Won);
+    }
+
+    #[test]
+    fn rejects_a_newer_version() {
+        let mut json: serde_json::Value = serde_json::from_str(&won_replay().to_json()).unwrap();
+        json["version"] = (REPLAY_VERSION + 1).into();
+        let err = Replay::from_json(&json.to_string()).unwrap_err();
+        assert!(matches!(
+            err,
+            ReplayError::File(FileError::UnsupportedVersion { found, supported, .. })
+                if found == REPLAY_VERSION + 1 && supported == REPLAY_VERSION
+        ));
+    }
+
+    #[test]
+    fn rejects_a_layout_that_does_not_fit() {
+        for layout in [vec![0], vec![0, 0], vec![0, 4]] {
+            let mut replay = won_replay();
+            replay.layout = layout;
+            assert!(matches!(replay.validate(), Err(ReplayError::BadLayout)));
+        }
+    }
+
+    #[test]
+    fn rejects_bad_moves() {
+        let mut replay = won_replay();
+        replay.events[0].action = Action::Reveal { row: 1, col: 0 };
+        assert!(matches!(
+            replay.validate(),
+            Err(ReplayError::CellOutOfRange { event: 0 })
+        ));
+
+        let mut replay = won_replay();
+        replay.events[1].at_ms = 500;
+        replay.events[2].at_ms = 100;
+        assert!(matches!(
+            replay.validate(),
+            Err(ReplayError::OutOfOrder { event: 2 })
+        ));
+    }
+
+    #[test]
+    fn rejects_a_result_the_moves_do_not_reach() {
+        let mut replay = won_replay();
+        replay.won = false;
+        assert!(matches!(
+            replay.validate(),
+            Err(ReplayError::OutcomeMismatch)
+        ));
+
+        let mut replay = won_replay();
+        replay.events.pop();
+        assert!(matches!(
+            replay.validate(),
+            Err(ReplayError::OutcomeMismatch)
+        ));
+    }
+}
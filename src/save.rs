@@ -0,0 +1,142 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::game::{ConfigError, Difficulty, Minesweeper};
+use crate::{FileError, FileFormat};
+
+/// Format version written to every saved game.
+pub const SAVE_VERSION: u32 = 1;
+
+const FORMAT: FileFormat = FileFormat::new("saved game", SAVE_VERSION);
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    game: &'a Minesweeper,
+}
+
+#[derive(Deserialize)]
+struct LoadFile {
+    game: Minesweeper,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    File(FileError),
+    Config(ConfigError),
+    Corrupt,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::File(err) => write!(f, "{}", err),
+            SaveError::Config(err) => write!(f, "Invalid board: {}", err),
+            SaveError::Corrupt => write!(f, "Saved board is damaged"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::File(err) => err.source(),
+            SaveError::Config(err) => Some(err),
+            SaveError::Corrupt => None,
+        }
+    }
+}
+
+impl From<FileError> for SaveError {
+    fn from(err: FileError) -> Self {
+        SaveError::File(err)
+    }
+}
+
+/// Where the game in progress is kept between sessions.
+pub fn autosave_path() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("autosave.json"))
+}
+
+pub fn to_json(game: &Minesweeper) -> String {
+    FORMAT.to_json(&SaveFile { game })
+}
+
+// Checks that a parsed board is playable, complete and has the flags it counts
+fn checked(file: LoadFile) -> Result<Minesweeper, SaveError> {
+    let LoadFile { game } = file;
+    Difficulty::from_config(game.rows, game.cols, game.mines)
+        .validate()
+        .map_err(SaveError::Config)?;
+    if game.cells.len() != (game.rows * game.cols) as usize || !game.flag_count_matches() {
+        return Err(SaveError::Corrupt);
+    }
+    Ok(game)
+}
+
+/// Parses a saved game, checking that its board is playable and complete.
+pub fn from_json(json: &str) -> Result<Minesweeper, SaveError> {
+    checked(FORMAT.from_json(json)?)
+}
+
+pub fn save_game(game: &Minesweeper, path: &Path) -> Result<(), SaveError> {
+    Ok(FORMAT.save(path, &SaveFile { game })?)
+}
+
+/// Opens a saved game. It may have been edited or played elsewhere, so it
+/// is marked imported and kept out of records.
+pub fn load_game(path: &Path) -> Result<Minesweeper, SaveError> {
+    let mut game = checked(FORMAT.load(path)?)?;
+    game.imported = true;
+    Ok(game)
+}
+
+/// Reads back the game this app left unfinished, which still counts.
+pub fn load_autosave(path: &Path) -> Result<Minesweeper, SaveError> {
+    checked(FORMAT.load(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameStatus;
+
+    fn game_in_play() -> Minesweeper {
+        let mut game = Minesweeper::with_seed(Difficulty::Beginner, 42);
+        game.reveal(4, 4).unwrap();
+        let mine = game.mine_layout()[0] as u32;
+        game.toggle_flag(mine / 9, mine % 9).unwrap();
+        // Stopped, so the time saved is the time compared
+        game.pause();
+        game
+    }
+
+    #[test]
+    fn round_trips_through_a_file() {
+        let game = game_in_play();
+        let path = std::env::temp_dir()
+            .join(format!("minesweep-test-{}", std::process::id()))
+            .join("game.json");
+        save_game(&game, &path).unwrap();
+        let loaded = load_autosave(&path);
+        let opened = load_game(&path);
+        std::fs::remove_dir_all(path.parent().unwrap()).ok();
+
+        let loaded = loaded.unwrap();
+        assert_eq!(to_json(&loaded), to_json(&game));
+        assert_eq!(loaded.status, GameStatus::Playing);
+        assert_eq!(loaded.flags_placed, 1);
+        assert!(!loaded.imported);
+        assert!(opened.unwrap().imported);
+    }
+
+    #[test]
+    fn rejects_a_flag_count_the_board_disagrees_with() {
+        let mut game = game_in_play();
+        game.flags_placed = 0;
+        assert!(matches!(
+            from_json(&to_json(&game)),
+            Err(SaveError::Corrupt)
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+use gpui::*;
+
+use crate::text_field::TextField;
+use crate::theme::theme;
+use crate::{MinesweeperView, bevel_button};
+
+// Starts a game of the current difficulty from a seed someone shared.
+pub struct SeedDialog {
+    seed: Entity<TextField>,
+    error: Option<SharedString>,
+    game_window: WindowHandle<MinesweeperView>,
+}
+
+impl SeedDialog {
+    pub fn new(
+        current: u64,
+        game_window: WindowHandle<MinesweeperView>,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let digits = u64::MAX.to_string().len();
+        let seed = cx.new(|cx| TextField::numeric(current, digits, cx).with_width(px(150.0)));
+        seed.read(cx).focus_handle(cx).focus(window);
+        Self {
+            seed,
+            error: None,
+            game_window,
+        }
+    }
+
+    fn confirm(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        match self.seed.read(cx).text().parse::<u64>() {
+            Ok(seed) => {
+                self.game_window
+                    .update(cx, |view, window, cx| view.start_seeded(seed, window, cx))
+                    .ok();
+                window.remove_window();
+            }
+            Err(_) => {
+                self.error = Some(format!("Seed must be 0 to {}", u64::MAX).into());
+                cx.notify();
+            }
+        }
+    }
+
+    fn handle_key_down(
+        &mut self,
+        event: &KeyDownEvent,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) {
+        match event.keystroke.key.as_str() {
+            "enter" => self.confirm(window, cx),
+            "escape" => window.remove_window(),
+            _ => {}
+        }
+    }
+}
+
+impl Render for SeedDialog {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = theme(cx).clone();
+        div()
+            .on_key_down(cx.listener(Self::handle_key_down))
+            .size_full()
+            .bg(theme.face)
+            .text_color(theme.text)
+            .p(px(10.0))
+            .flex()
+            .flex_col()
+            .gap(px(6.0))
+            .child(div().text_size(px(12.0)).child("Seed:"))
+            .child(self.seed.clone())
+            .child(
+                div()
+                    .h(px(14.0))
+                    .text_size(px(11.0))
+                    .text_color(theme.error_text)
+                    .children(self.error.clone()),
+            )
+            .child(
+                div()
+                    .flex()
+                    .justify_end()
+                    .gap(px(6.0))
+                    .child(bevel_button(&theme, "OK").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _, window, cx| view.confirm(window, cx)),
+                    ))
+                    .child(bevel_button(&theme, "Cancel").on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|_, _, window, _| window.remove_window()),
+                    )),
+            )
+    }
+}
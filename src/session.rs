@@ -0,0 +1,147 @@
+use gpui::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{MinesweeperView, show_error};
+use minesweep::data_dir;
+use minesweep::game::{Difficulty, GameStatus, Minesweeper};
+use minesweep::save::{SaveError, autosave_path, load_autosave, load_game, save_game};
+
+// Saving, opening, the clipboard and the autosave that carries a game over to the next launch
+impl MinesweeperView {
+    pub fn save_game(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let dir = data_dir()
+            .or_else(dirs::home_dir)
+            .unwrap_or_else(|| PathBuf::from("."));
+        let path = cx.prompt_for_new_path(&dir, Some("minesweeper.json"));
+        cx.spawn_in(window, async move |view, cx| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            view.update_in(cx, |view, window, cx| {
+                if let Err(err) = save_game(&view.game, &path) {
+                    show_error(window, "Could not save game", &err.to_string(), cx);
+                }
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub fn open_game(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Open Game".into()),
+        });
+        cx.spawn_in(window, async move |view, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            view.update_in(cx, |view, window, cx| match load_game(&path) {
+                Ok(game) => view.resume(game, window, cx),
+                Err(err) => show_error(window, "Could not open game", &err.to_string(), cx),
+            })
+            .ok();
+        })
+        .detach();
+    }
+
+    pub fn copy_board(&mut self, cx: &mut Context<Self>) {
+        cx.write_to_clipboard(ClipboardItem::new_string(self.game.to_text()));
+    }
+
+    pub fn paste_board(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(text) = cx.read_from_clipboard().and_then(|item| item.text()) else {
+            return;
+        };
+        match Minesweeper::from_text(&text) {
+            Ok(game) => self.resume(game, window, cx),
+            Err(err) => show_error(window, "Clipboard has no board", &err.to_string(), cx),
+        }
+    }
+
+    pub fn resume(&mut self, mut game: Minesweeper, window: &mut Window, cx: &mut Context<Self>) {
+        // Loaded games have their clock stopped
+        game.resume();
+        let difficulty = Difficulty::from_config(game.rows, game.cols, game.mines);
+        self.start_game(difficulty, game, window, cx);
+    }
+
+    /// Keeps an unfinished game for the next launch, or clears a stale one.
+    pub fn autosave(&self) -> Result<(), SaveError> {
+        let Some(path) = autosave_path() else {
+            return Ok(());
+        };
+        if self.game.status == GameStatus::Playing && self.playback.is_none() {
+            save_game(&self.game, &path)?;
+        } else if path.exists() {
+            fs::remove_file(&path).ok();
+        }
+        Ok(())
+    }
+
+    /// Autosaves before the window goes, and returns whether it may close now.
+    /// If the game can't be kept the player is asked first, and the window is
+    /// closed later if they don't mind losing it.
+    pub fn close_window(&mut self, window: &mut Window, cx: &mut Context<Self>) -> bool {
+        let Err(err) = self.autosave() else {
+            return true;
+        };
+        let answer = window.prompt(
+            PromptLevel::Warning,
+            "Your game could not be kept for next time",
+            Some(&err.to_string()),
+            &["Close Anyway", "Keep Playing"],
+            cx,
+        );
+        cx.spawn_in(window, async move |_, cx| {
+            if answer.await == Ok(0) {
+                cx.update(|window, _| window.remove_window()).ok();
+            }
+        })
+        .detach();
+        false
+    }
+
+    /// Asks whether to pick up the game left unfinished last time. The
+    /// autosave is used up either way.
+    pub fn offer_resume(&mut self, window: &mut Window, cx: &mut Context<Self>) {
+        let Some(path) = autosave_path() else {
+            return;
+        };
+        let Ok(game) = load_autosave(&path) else {
+            return;
+        };
+        fs::remove_file(&path).ok();
+        if game.status != GameStatus::Playing {
+            return;
+        }
+        let detail = format!(
+            "{}x{} board with {} mines, {}s in",
+            game.cols,
+            game.rows,
+            game.mines,
+            game.elapsed_seconds()
+        );
+        let answer = window.prompt(
+            PromptLevel::Info,
+            "Resume your last game?",
+            Some(&detail),
+            &["Resume", "New Game"],
+            cx,
+        );
+        cx.spawn_in(window, async move |view, cx| {
+            if answer.await != Ok(0) {
+                return;
+            }
+            view.update_in(cx, |view, window, cx| view.resume(game, window, cx))
+                .ok();
+        })
+        .detach();
+    }
+}
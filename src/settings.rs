@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{FileError, FileFormat};
+
+/// Format version written to the settings file.
+pub const SETTINGS_VERSION: u32 = 1;
+
+const FORMAT: FileFormat = FileFormat::new("settings", SETTINGS_VERSION);
+
+/// The built-in looks; the frontend decides the colors of each.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ThemeName {
+    #[default]
+    Win2000,
+    Xp,
+    Dark,
+    HighContrast,
+}
+
+impl ThemeName {
+    pub const ALL: [ThemeName; 4] = [
+        ThemeName::Win2000,
+        ThemeName::Xp,
+        ThemeName::Dark,
+        ThemeName::HighContrast,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ThemeName::Win2000 => "Classic (Windows 2000)",
+            ThemeName::Xp => "Windows XP",
+            ThemeName::Dark => "Dark",
+            ThemeName::HighContrast => "High Contrast",
+        }
+    }
+}
+
+/// Choices remembered between launches. Anything left out of the file
+/// keeps its default.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeName,
+}
+
+/// Where settings are kept, e.g. `~/.local/share/minesweep/settings.json`.
+pub fn settings_path() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("settings.json"))
+}
+
+impl Settings {
+    pub fn to_json(&self) -> String {
+        FORMAT.to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, FileError> {
+        FORMAT.from_json(json)
+    }
+
+    /// Reads the settings file; a missing file means the defaults.
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        FORMAT.load_or_default(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        FORMAT.save(path, self)
+    }
+}
@@ -0,0 +1,722 @@
+use crate::game::{CellContent, CellState, Minesweeper};
+
+// Largest frontier component the global step will enumerate
+pub const DEFAULT_ENUMERATION_LIMIT: usize = 32;
+// Backtracking nodes allowed per component before giving up on it
+const NODE_BUDGET: usize = 200_000;
+
+/// What a player can see of a cell.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Visible {
+    Unknown,
+    Mine, // Flagged, or otherwise known to be a mine
+    Clear(u8),
+}
+
+/// The player-visible state of a board, plus the cell adjacency.
+///
+/// Built from a `Minesweeper` without ever looking at the content of a cell
+/// that is not revealed. Flags are trusted as mines.
+#[derive(Clone, Debug)]
+pub struct Board {
+    pub rows: u32,
+    pub cols: u32,
+    pub mines: u32,
+    pub cells: Vec<Visible>,
+    pub neighbors: Vec<Vec<usize>>,
+}
+
+impl Board {
+    pub fn from_game(game: &Minesweeper) -> Self {
+        let mut board = Self::unknown(game);
+        for (idx, cell) in game.cells.iter().enumerate() {
+            board.cells[idx] = match cell.state {
+                CellState::Revealed => match cell.content {
+                    CellContent::Empty => Visible::Clear(0),
+                    CellContent::Number(n) => Visible::Clear(n),
+                    // Only visible once the game is lost
+                    CellContent::Mine(_) => Visible::Mine,
+                },
+                CellState::Flagged(_) => Visible::Mine,
+                CellState::Hidden | CellState::QuestionMark => Visible::Unknown,
+            };
+        }
+        board
+    }
+
+    /// Same dimensions and mine count as `game`, with every cell unknown.
+    pub fn unknown(game: &Minesweeper) -> Self {
+        let neighbors = (0..game.rows)
+            .flat_map(|r| (0..game.cols).map(move |c| (r, c)))
+            .map(|(r, c)| {
+                game.neighbors(r, c)
+                    .into_iter()
+                    .map(|(nr, nc)| game.index(nr, nc))
+                    .collect()
+            })
+            .collect();
+        Self {
+            rows: game.rows,
+            cols: game.cols,
+            mines: game.mines,
+            cells: vec![Visible::Unknown; (game.rows * game.cols) as usize],
+            neighbors,
+        }
+    }
+
+    pub fn position(&self, idx: usize) -> (u32, u32) {
+        (idx as u32 / self.cols, idx as u32 % self.cols)
+    }
+
+    /// A board from rows of `#` for unknown, `F` for a known mine and digits
+    /// for revealed numbers, with the classic eight neighbours.
+    #[cfg(test)]
+    pub(crate) fn parse(mines: u32, rows: &[&str]) -> Self {
+        let (height, width) = (rows.len() as i32, rows[0].len() as i32);
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '#' => Visible::Unknown,
+                'F' => Visible::Mine,
+                _ => Visible::Clear(c.to_digit(10).expect("cell character") as u8),
+            })
+            .collect();
+        let neighbors = (0..height)
+            .flat_map(|r| (0..width).map(move |c| (r, c)))
+            .map(|(r, c)| {
+                (-1..=1)
+                    .flat_map(|dr| (-1..=1).map(move |dc| (r + dr, c + dc)))
+                    .filter(|&(nr, nc)| (nr, nc) != (r, c))
+                    .filter(|&(nr, nc)| (0..height).contains(&nr) && (0..width).contains(&nc))
+                    .map(|(nr, nc)| (nr * width + nc) as usize)
+                    .collect()
+            })
+            .collect();
+        Self {
+            rows: height as u32,
+            cols: width as u32,
+            mines,
+            cells,
+            neighbors,
+        }
+    }
+
+    fn known_mines(&self) -> usize {
+        self.cells.iter().filter(|&&c| c == Visible::Mine).count()
+    }
+}
+
+/// Cells proven safe or proven to be mines.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Deductions {
+    pub safe: Vec<(u32, u32)>,
+    pub mines: Vec<(u32, u32)>,
+}
+
+impl Deductions {
+    pub fn is_empty(&self) -> bool {
+        self.safe.is_empty() && self.mines.is_empty()
+    }
+}
+
+/// A revealed number: `need` mines among the still unknown `cells`.
+#[derive(Clone, Debug)]
+pub(crate) struct Constraint {
+    pub cells: Vec<usize>, // Sorted
+    pub need: usize,
+}
+
+/// Unknown cells touched by constraints, split into independent groups, with
+/// every consistent assignment of each group counted per number of mines.
+pub(crate) struct Frontier {
+    pub components: Vec<Component>,
+    // Unknown cells next to no revealed number
+    pub interior: Vec<usize>,
+    // Mines not yet accounted for by known mines
+    pub remaining: usize,
+}
+
+pub(crate) struct Component {
+    pub cells: Vec<usize>,
+    // None when the component was too large to enumerate
+    pub tally: Option<Tally>,
+}
+
+pub(crate) struct Tally {
+    // solutions[k]: assignments placing k mines
+    pub solutions: Vec<f64>,
+    // mine_counts[k][i]: of those, how many put a mine on cells[i]
+    pub mine_counts: Vec<Vec<f64>>,
+}
+
+impl Component {
+    pub fn mine_range(&self) -> (usize, usize) {
+        match &self.tally {
+            Some(tally) => {
+                let mut feasible = (0..tally.solutions.len()).filter(|&k| tally.solutions[k] > 0.0);
+                let min = feasible.next().unwrap_or(0);
+                let max = feasible.next_back().unwrap_or(min);
+                (min, max)
+            }
+            None => (0, self.cells.len()),
+        }
+    }
+
+    pub fn feasible(&self, k: usize) -> bool {
+        match &self.tally {
+            Some(tally) => tally.solutions.get(k).is_some_and(|&n| n > 0.0),
+            None => k <= self.cells.len(),
+        }
+    }
+}
+
+/// Finds provably safe cells and provable mines from visible state.
+///
+/// Runs single-cell rules, then subset/superset reasoning between pairs of
+/// numbers, then an exhaustive enumeration of each frontier group (bounded by
+/// `enumeration_limit`) combined with the global mine count. It stops at the
+/// first stage that proves something, so callers wanting every deduction
+/// should apply the result and ask again.
+#[derive(Clone, Copy, Debug)]
+pub struct Solver {
+    pub enumeration_limit: usize,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self {
+            enumeration_limit: DEFAULT_ENUMERATION_LIMIT,
+        }
+    }
+}
+
+impl Solver {
+    pub fn deduce(&self, board: &Board) -> Deductions {
+        let constraints = constraints(board);
+
+        let (safe, mines) = single_cell(&constraints);
+        if !safe.is_empty() || !mines.is_empty() {
+            return to_deductions(board, safe, mines);
+        }
+
+        let (safe, mines) = subsets(board, &constraints);
+        if !safe.is_empty() || !mines.is_empty() {
+            return to_deductions(board, safe, mines);
+        }
+
+        let frontier = enumerate(board, &constraints, self.enumeration_limit);
+        let (safe, mines) = global(&frontier);
+        to_deductions(board, safe, mines)
+    }
+
+    /// A provably safe cell, marking proven mines on a copy of the board
+    /// until one turns up.
+    pub fn find_safe(&self, board: &Board) -> Option<(u32, u32)> {
+        let mut board = board.clone();
+        loop {
+            let deductions = self.deduce(&board);
+            if let Some(&cell) = deductions.safe.first() {
+                return Some(cell);
+            }
+            if deductions.mines.is_empty() {
+                return None;
+            }
+            for (row, col) in deductions.mines {
+                board.cells[(row * board.cols + col) as usize] = Visible::Mine;
+            }
+        }
+    }
+}
+
+/// Deductions for the current visible state of `game` with the default solver.
+pub fn deduce(game: &Minesweeper) -> Deductions {
+    Solver::default().deduce(&Board::from_game(game))
+}
+
+fn to_deductions(board: &Board, mut safe: Vec<usize>, mut mines: Vec<usize>) -> Deductions {
+    safe.sort_unstable();
+    safe.dedup();
+    mines.sort_unstable();
+    mines.dedup();
+    Deductions {
+        safe: safe.into_iter().map(|i| board.position(i)).collect(),
+        mines: mines.into_iter().map(|i| board.position(i)).collect(),
+    }
+}
+
+pub(crate) fn constraints(board: &Board) -> Vec<Constraint> {
+    let mut constraints = Vec::new();
+    for (idx, cell) in board.cells.iter().enumerate() {
+        let Visible::Clear(n) = *cell else {
+            continue;
+        };
+        let mut unknown = Vec::new();
+        let mut mines = 0;
+        for &n_idx in &board.neighbors[idx] {
+            match board.cells[n_idx] {
+                Visible::Unknown => unknown.push(n_idx),
+                Visible::Mine => mines += 1,
+                Visible::Clear(_) => {}
+            }
+        }
+        // A wrong flag can make a number over-satisfied; nothing sound follows from it
+        if unknown.is_empty() || mines > n as usize {
+            continue;
+        }
+        unknown.sort_unstable();
+        constraints.push(Constraint {
+            cells: unknown,
+            need: n as usize - mines,
+        });
+    }
+    constraints
+}
+
+fn single_cell(constraints: &[Constraint]) -> (Vec<usize>, Vec<usize>) {
+    let mut safe = Vec::new();
+    let mut mines = Vec::new();
+    for constraint in constraints {
+        if constraint.need == 0 {
+            safe.extend(&constraint.cells);
+        } else if constraint.need == constraint.cells.len() {
+            mines.extend(&constraint.cells);
+        }
+    }
+    (safe, mines)
+}
+
+fn subsets(board: &Board, constraints: &[Constraint]) -> (Vec<usize>, Vec<usize>) {
+    // Only constraints sharing a cell can be subsets of each other
+    let mut by_cell: Vec<Vec<usize>> = vec![Vec::new(); board.cells.len()];
+    for (i, constraint) in constraints.iter().enumerate() {
+        for &cell in &constraint.cells {
+            by_cell[cell].push(i);
+        }
+    }
+
+    let mut safe = Vec::new();
+    let mut mines = Vec::new();
+    for a in constraints {
+        let mut candidates: Vec<usize> =
+            a.cells.iter().flat_map(|&c| &by_cell[c]).copied().collect();
+        candidates.sort_unstable();
+        candidates.dedup();
+        for b in candidates.into_iter().map(|i| &constraints[i]) {
+            if a.cells.len() >= b.cells.len()
+                || !a.cells.iter().all(|x| b.cells.binary_search(x).is_ok())
+            {
+                continue;
+            }
+            let rest: Vec<usize> = b
+                .cells
+                .iter()
+                .copied()
+                .filter(|x| a.cells.binary_search(x).is_err())
+                .collect();
+            if b.need == a.need {
+                safe.extend(&rest);
+            } else if b.need.checked_sub(a.need) == Some(rest.len()) {
+                mines.extend(&rest);
+            }
+        }
+    }
+    (safe, mines)
+}
+
+pub(crate) fn enumerate(board: &Board, constraints: &[Constraint], limit: usize) -> Frontier {
+    let total = board.cells.len();
+
+    // Union constraints that share cells into components
+    let mut component_of: Vec<Option<usize>> = vec![None; total];
+    let mut groups: Vec<Vec<usize>> = Vec::new(); // constraint indices per component
+    let mut by_cell: Vec<Vec<usize>> = vec![Vec::new(); total];
+    for (i, constraint) in constraints.iter().enumerate() {
+        for &cell in &constraint.cells {
+            by_cell[cell].push(i);
+        }
+    }
+    let mut seen = vec![false; constraints.len()];
+    for start in 0..constraints.len() {
+        if seen[start] {
+            continue;
+        }
+        let id = groups.len();
+        let mut group = Vec::new();
+        let mut stack = vec![start];
+        seen[start] = true;
+        while let Some(ci) = stack.pop() {
+            group.push(ci);
+            for &cell in &constraints[ci].cells {
+                component_of[cell] = Some(id);
+                for &other in &by_cell[cell] {
+                    if !seen[other] {
+                        seen[other] = true;
+                        stack.push(other);
+                    }
+                }
+            }
+        }
+        groups.push(group);
+    }
+
+    let mut components: Vec<Component> = groups
+        .iter()
+        .map(|group| {
+            let mut cells: Vec<usize> = group
+                .iter()
+                .flat_map(|&ci| constraints[ci].cells.iter().copied())
+                .collect();
+            cells.sort_unstable();
+            cells.dedup();
+            Component { cells, tally: None }
+        })
+        .collect();
+
+    for (component, group) in components.iter_mut().zip(&groups) {
+        if component.cells.len() <= limit {
+            let group: Vec<&Constraint> = group.iter().map(|&ci| &constraints[ci]).collect();
+            component.tally = tally(&component.cells, &group);
+        }
+    }
+
+    let interior = (0..total)
+        .filter(|&i| board.cells[i] == Visible::Unknown && component_of[i].is_none())
+        .collect();
+    let remaining = (board.mines as usize).saturating_sub(board.known_mines());
+
+    Frontier {
+        components,
+        interior,
+        remaining,
+    }
+}
+
+// Counts every assignment of mines to `cells` that satisfies `constraints`.
+// None if the search exceeds the node budget.
+fn tally(cells: &[usize], constraints: &[&Constraint]) -> Option<Tally> {
+    // Constraints in terms of positions within `cells`
+    let local: Vec<(Vec<usize>, usize)> = constraints
+        .iter()
+        .map(|c| {
+            let members = c
+                .cells
+                .iter()
+                .map(|x| {
+                    cells
+                        .binary_search(x)
+                        .expect("constraint cell in component")
+                })
+                .collect();
+            (members, c.need)
+        })
+        .collect();
+    let mut touching: Vec<Vec<usize>> = vec![Vec::new(); cells.len()];
+    for (ci, (members, _)) in local.iter().enumerate() {
+        for &m in members {
+            touching[m].push(ci);
+        }
+    }
+
+    struct Search<'a> {
+        local: &'a [(Vec<usize>, usize)],
+        touching: &'a [Vec<usize>],
+        placed: Vec<usize>,     // Mines assigned per constraint
+        unassigned: Vec<usize>, // Cells still open per constraint
+        assignment: Vec<bool>,
+        tally: Tally,
+        nodes: usize,
+    }
+
+    impl Search<'_> {
+        fn run(&mut self, pos: usize, mines: usize) -> bool {
+            self.nodes += 1;
+            if self.nodes > NODE_BUDGET {
+                return false;
+            }
+            if pos == self.assignment.len() {
+                self.tally.solutions[mines] += 1.0;
+                for (i, &mine) in self.assignment.iter().enumerate() {
+                    if mine {
+                        self.tally.mine_counts[mines][i] += 1.0;
+                    }
+                }
+                return true;
+            }
+            for mine in [false, true] {
+                let ok = self.touching[pos].iter().all(|&ci| {
+                    let placed = self.placed[ci] + mine as usize;
+                    let open = self.unassigned[ci] - 1;
+                    placed <= self.local[ci].1 && placed + open >= self.local[ci].1
+                });
+                if !ok {
+                    continue;
+                }
+                for &ci in &self.touching[pos] {
+                    self.placed[ci] += mine as usize;
+                    self.unassigned[ci] -= 1;
+                }
+                self.assignment[pos] = mine;
+                let finished = self.run(pos + 1, mines + mine as usize);
+                for &ci in &self.touching[pos] {
+                    self.placed[ci] -= mine as usize;
+                    self.unassigned[ci] += 1;
+                }
+                if !finished {
+                    return false;
+                }
+            }
+            self.assignment[pos] = false;
+            true
+        }
+    }
+
+    let n = cells.len();
+    let mut search = Search {
+        local: &local,
+        touching: &touching,
+        placed: vec![0; local.len()],
+        unassigned: local.iter().map(|(members, _)| members.len()).collect(),
+        assignment: vec![false; n],
+        tally: Tally {
+            solutions: vec![0.0; n + 1],
+            mine_counts: vec![vec![0.0; n]; n + 1],
+        },
+        nodes: 0,
+    };
+    if search.run(0, 0) {
+        Some(search.tally)
+    } else {
+        None
+    }
+}
+
+impl Frontier {
+    /// For each component, which mine counts are possible for it once every
+    /// other component and the interior are taken into account.
+    pub fn globally_feasible(&self) -> Vec<Vec<bool>> {
+        let n = self.components.len();
+        let cap = self.remaining;
+        // Achievable mine sums over components [0, i) and (i, n)
+        let step = |sums: &Vec<bool>, component: &Component| {
+            let mut next = vec![false; cap + 1];
+            let (lo, hi) = component.mine_range();
+            for (s, _) in sums.iter().enumerate().filter(|&(_, &ok)| ok) {
+                for k in lo..=hi {
+                    if s + k <= cap && component.feasible(k) {
+                        next[s + k] = true;
+                    }
+                }
+            }
+            next
+        };
+        let mut empty = vec![false; cap + 1];
+        empty[0] = true;
+        let mut prefix = vec![empty.clone()];
+        for component in &self.components {
+            let next = step(prefix.last().unwrap(), component);
+            prefix.push(next);
+        }
+        let mut suffix = vec![empty];
+        for component in self.components.iter().rev() {
+            let next = step(suffix.last().unwrap(), component);
+            suffix.push(next);
+        }
+        suffix.reverse();
+
+        let interior = self.interior.len();
+        (0..n)
+            .map(|i| {
+                let component = &self.components[i];
+                // Sums the other components can reach together
+                let mut others = vec![false; cap + 1];
+                for (a, _) in prefix[i].iter().enumerate().filter(|&(_, &ok)| ok) {
+                    for (b, _) in suffix[i + 1].iter().enumerate().filter(|&(_, &ok)| ok) {
+                        if a + b <= cap {
+                            others[a + b] = true;
+                        }
+                    }
+                }
+                (0..=component.cells.len())
+                    .map(|k| {
+                        component.feasible(k)
+                            && others
+                                .iter()
+                                .enumerate()
+                                .any(|(s, &ok)| ok && s + k <= cap && cap - s - k <= interior)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Possible numbers of mines left over for the interior cells.
+    pub fn interior_feasible(&self) -> Vec<bool> {
+        let cap = self.remaining;
+        let mut sums = vec![false; cap + 1];
+        sums[0] = true;
+        for component in &self.components {
+            let mut next = vec![false; cap + 1];
+            for (s, _) in sums.iter().enumerate().filter(|&(_, &ok)| ok) {
+                for k in 0..=component.cells.len() {
+                    if s + k <= cap && component.feasible(k) {
+                        next[s + k] = true;
+                    }
+                }
+            }
+            sums = next;
+        }
+        let interior = self.interior.len();
+        (0..=interior).map(|m| m <= cap && sums[cap - m]).collect()
+    }
+}
+
+fn global(frontier: &Frontier) -> (Vec<usize>, Vec<usize>) {
+    let mut safe = Vec::new();
+    let mut mines = Vec::new();
+
+    let feasible = frontier.globally_feasible();
+    for (component, feasible) in frontier.components.iter().zip(&feasible) {
+        let Some(tally) = &component.tally else {
+            continue;
+        };
+        let ks: Vec<usize> = (0..feasible.len()).filter(|&k| feasible[k]).collect();
+        // Contradictory flags leave no solutions; claim nothing then
+        if ks.is_empty() {
+            continue;
+        }
+        for (i, &cell) in component.cells.iter().enumerate() {
+            if ks.iter().all(|&k| tally.mine_counts[k][i] == 0.0) {
+                safe.push(cell);
+            } else if ks
+                .iter()
+                .all(|&k| tally.mine_counts[k][i] == tally.solutions[k])
+            {
+                mines.push(cell);
+            }
+        }
+    }
+
+    let interior = frontier.interior.len();
+    if interior > 0 {
+        let counts: Vec<usize> = frontier
+            .interior_feasible()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &ok)| ok)
+            .map(|(m, _)| m)
+            .collect();
+        if counts == [0] {
+            safe.extend(&frontier.interior);
+        } else if counts == [interior] {
+            mines.extend(&frontier.interior);
+        }
+    }
+
+    (safe, mines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deductions(safe: &[(u32, u32)], mines: &[(u32, u32)]) -> Deductions {
+        Deductions {
+            safe: safe.to_vec(),
+            mines: mines.to_vec(),
+        }
+    }
+
+    #[test]
+    fn single_cell_rules() {
+        let solver = Solver::default();
+        assert_eq!(
+            solver.deduce(&Board::parse(1, &["1#"])),
+            deductions(&[], &[(0, 1)])
+        );
+        assert_eq!(
+            solver.deduce(&Board::parse(1, &["F1#"])),
+            deductions(&[(0, 2)], &[])
+        );
+    }
+
+    #[test]
+    fn one_one_pattern_clears_past_the_shared_cells() {
+        // The left 1 has its mine among the two above it, and the middle 1
+        // shares those, so the rest of what it touches is safe
+        let board = Board::parse(2, &["###", "11#"]);
+        assert_eq!(
+            Solver::default().deduce(&board),
+            deductions(&[(0, 2), (1, 2)], &[])
+        );
+    }
+
+    #[test]
+    fn one_two_one_pattern_finds_the_outer_mines() {
+        let board = Board::parse(2, &["###", "121"]);
+        assert_eq!(
+            Solver::default().deduce(&board),
+            deductions(&[], &[(0, 0), (0, 2)])
+        );
+    }
+
+    #[test]
+    fn find_safe_applies_proven_mines_until_a_safe_cell_shows() {
+        let solver = Solver::default();
+        assert_eq!(
+            solver.find_safe(&Board::parse(2, &["###", "121"])),
+            Some((0, 1))
+        );
+        assert_eq!(solver.find_safe(&Board::parse(1, &["#1#"])), None);
+    }
+
+    #[test]
+    fn enumeration_uses_the_mine_count() {
+        // Either the middle cell is the only mine, or both ends are mines
+        let solver = Solver::default();
+        assert_eq!(
+            solver.deduce(&Board::parse(1, &["#1#1#"])),
+            deductions(&[(0, 0), (0, 4)], &[(0, 2)])
+        );
+        assert_eq!(
+            solver.deduce(&Board::parse(2, &["#1#1#"])),
+            deductions(&[(0, 2)], &[(0, 0), (0, 4)])
+        );
+        // A cell beyond the numbers can take the mine the middle leaves over
+        assert_eq!(
+            solver.deduce(&Board::parse(2, &["#1#1##"])),
+            Deductions::default()
+        );
+
+        // Too large to enumerate, so the count can't be used
+        let limited = Solver {
+            enumeration_limit: 2,
+        };
+        assert_eq!(
+            limited.deduce(&Board::parse(1, &["#1#1#"])),
+            Deductions::default()
+        );
+    }
+
+    #[test]
+    fn mine_count_settles_the_interior() {
+        // The 1 takes one mine whichever side it is on; the last cell gets the rest
+        let solver = Solver::default();
+        assert_eq!(
+            solver.deduce(&Board::parse(1, &["#1##"])),
+            deductions(&[(0, 3)], &[])
+        );
+        assert_eq!(
+            solver.deduce(&Board::parse(2, &["#1##"])),
+            deductions(&[], &[(0, 3)])
+        );
+        assert_eq!(
+            solver.deduce(&Board::parse(3, &["#1###"])),
+            deductions(&[], &[(0, 3), (0, 4)])
+        );
+        assert_eq!(
+            solver.deduce(&Board::parse(2, &["#1###"])),
+            Deductions::default()
+        );
+    }
+}
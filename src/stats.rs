@@ -0,0 +1,351 @@
+use chrono::{DateTime, FixedOffset, Local};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::game::{Difficulty, GameStatus, Minesweeper};
+use crate::{FileError, FileFormat};
+
+/// Format version written to the statistics file.
+pub const STATS_VERSION: u32 = 1;
+
+const FORMAT: FileFormat = FileFormat::new("statistics", STATS_VERSION);
+
+/// How many of the fastest wins each difficulty keeps names for.
+pub const BEST_TIMES: usize = 10;
+
+/// One finished game.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct GameRecord {
+    pub date: String, // RFC 3339, local time
+    pub rows: u32,
+    pub cols: u32,
+    pub mines: u32,
+    pub seed: u64,
+    pub time_ms: u64,
+    pub won: bool,
+    pub bbbv: u32,
+    /// Asked for when a win makes the best times.
+    #[serde(default)]
+    pub name: String,
+}
+
+impl GameRecord {
+    /// Captures a won or lost game, finished just now. Games that had hints
+    /// or undos, were variants, or were pasted or opened, are left out.
+    pub fn from_game(game: &Minesweeper) -> Option<Self> {
+        if game.is_assisted() || game.is_variant() || game.imported {
+            return None;
+        }
+        let won = match game.status {
+            GameStatus::Won => true,
+            GameStatus::Lost => false,
+            _ => return None,
+        };
+        Some(Self {
+            date: Local::now().to_rfc3339(),
+            rows: game.rows,
+            cols: game.cols,
+            mines: game.mines,
+            seed: game.seed,
+            time_ms: game.elapsed_ms(),
+            won,
+            bbbv: game.analyze().map_or(0, |stats| stats.bbbv),
+            name: String::new(),
+        })
+    }
+
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::from_config(self.rows, self.cols, self.mines)
+    }
+
+    pub fn date(&self) -> Option<DateTime<FixedOffset>> {
+        DateTime::parse_from_rfc3339(&self.date).ok()
+    }
+}
+
+/// Totals for one difficulty.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Summary {
+    pub played: u32,
+    pub won: u32,
+    /// Fastest win, in seconds.
+    pub best_time: Option<f32>,
+    /// Mean time of won games.
+    pub average_time: Option<f32>,
+    /// Wins since the last loss.
+    pub current_streak: u32,
+    pub longest_streak: u32,
+}
+
+impl Summary {
+    pub fn win_percent(&self) -> f32 {
+        if self.played == 0 {
+            0.0
+        } else {
+            self.won as f32 * 100.0 / self.played as f32
+        }
+    }
+}
+
+/// Every finished game, oldest first.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Stats {
+    games: Vec<GameRecord>,
+    #[serde(default)]
+    player: String, // Last name entered, offered for the next record
+}
+
+/// Where finished games are kept, e.g. `~/.local/share/minesweep/stats.json`.
+pub fn stats_path() -> Option<PathBuf> {
+    crate::data_dir().map(|dir| dir.join("stats.json"))
+}
+
+impl Stats {
+    pub fn games(&self) -> &[GameRecord] {
+        &self.games
+    }
+
+    /// Adds a game and returns its index, for `remove` and `set_name`.
+    pub fn add(&mut self, record: GameRecord) -> usize {
+        self.games.push(record);
+        self.games.len() - 1
+    }
+
+    /// Takes a game back out, e.g. one that was undone after it ended. Later
+    /// games move down one.
+    pub fn remove(&mut self, index: usize) {
+        if index < self.games.len() {
+            self.games.remove(index);
+        }
+    }
+
+    /// Names a game, and remembers the name for next time.
+    pub fn set_name(&mut self, index: usize, name: &str) {
+        if let Some(game) = self.games.get_mut(index) {
+            game.name = name.to_string();
+            self.player = name.to_string();
+        }
+    }
+
+    pub fn player(&self) -> &str {
+        &self.player
+    }
+
+    /// Forgets every game.
+    pub fn clear(&mut self) {
+        self.games.clear();
+    }
+
+    /// The fastest wins of a difficulty with their indices, fastest first;
+    /// ties go to the earlier game.
+    pub fn best_times(&self, difficulty: Difficulty) -> Vec<(usize, &GameRecord)> {
+        let mut wins: Vec<_> = self
+            .games
+            .iter()
+            .enumerate()
+            .filter(|(_, game)| game.won && game.difficulty() == difficulty)
+            .collect();
+        wins.sort_by_key(|&(index, game)| (game.time_ms, index));
+        wins.truncate(BEST_TIMES);
+        wins
+    }
+
+    /// Whether a game is among the best times of its difficulty.
+    pub fn is_best_time(&self, index: usize) -> bool {
+        self.games.get(index).is_some_and(|game| {
+            self.best_times(game.difficulty())
+                .iter()
+                .any(|&(best, _)| best == index)
+        })
+    }
+
+    /// Won games of a difficulty counted into `buckets` equal time ranges,
+    /// from zero to the slowest win. Returns the width of a range in seconds
+    /// and the counts.
+    pub fn time_histogram(&self, difficulty: Difficulty, buckets: usize) -> (u32, Vec<u32>) {
+        let times: Vec<u32> = self
+            .records(difficulty)
+            .filter(|game| game.won)
+            .map(|game| (game.time_ms / 1000) as u32)
+            .collect();
+        let slowest = times.iter().copied().max().unwrap_or(0);
+        let width = (slowest / buckets.max(1) as u32 + 1).max(1);
+        let mut counts = vec![0; buckets];
+        for time in times {
+            if let Some(count) = counts.get_mut((time / width) as usize) {
+                *count += 1;
+            }
+        }
+        (width, counts)
+    }
+
+    /// Difficulties with at least one game, presets first.
+    pub fn difficulties(&self) -> Vec<Difficulty> {
+        let mut difficulties = vec![
+            Difficulty::Beginner,
+            Difficulty::Intermediate,
+            Difficulty::Expert,
+        ];
+        for game in &self.games {
+            let difficulty = game.difficulty();
+            if !difficulties.contains(&difficulty) {
+                difficulties.push(difficulty);
+            }
+        }
+        difficulties.retain(|&difficulty| self.records(difficulty).next().is_some());
+        difficulties
+    }
+
+    /// Games of one difficulty, oldest first.
+    pub fn records(&self, difficulty: Difficulty) -> impl Iterator<Item = &GameRecord> {
+        self.games
+            .iter()
+            .filter(move |game| game.difficulty() == difficulty)
+    }
+
+    pub fn summary(&self, difficulty: Difficulty) -> Summary {
+        let mut summary = Summary::default();
+        let mut total_ms = 0u64;
+        for game in self.records(difficulty) {
+            summary.played += 1;
+            if game.won {
+                summary.won += 1;
+                let seconds = game.time_ms as f32 / 1000.0;
+                total_ms += game.time_ms;
+                summary.best_time =
+                    Some(summary.best_time.map_or(seconds, |best| best.min(seconds)));
+                summary.current_streak += 1;
+                summary.longest_streak = summary.longest_streak.max(summary.current_streak);
+            } else {
+                summary.current_streak = 0;
+            }
+        }
+        if summary.won > 0 {
+            summary.average_time = Some(total_ms as f32 / 1000.0 / summary.won as f32);
+        }
+        summary
+    }
+
+    pub fn to_json(&self) -> String {
+        FORMAT.to_json(self)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, FileError> {
+        FORMAT.from_json(json)
+    }
+
+    /// Reads the statistics file; a missing file is an empty history.
+    pub fn load(path: &Path) -> Result<Self, FileError> {
+        FORMAT.load_or_default(path)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), FileError> {
+        FORMAT.save(path, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(difficulty: Difficulty, won: bool, time_ms: u64) -> GameRecord {
+        let (rows, cols, mines) = difficulty.config();
+        GameRecord {
+            date: String::new(),
+            rows,
+            cols,
+            mines,
+            seed: 0,
+            time_ms,
+            won,
+            bbbv: 0,
+            name: String::new(),
+        }
+    }
+
+    fn history() -> Stats {
+        let mut stats = Stats::default();
+        for (won, time_ms) in [
+            (true, 12_000),
+            (true, 8_000),
+            (false, 3_000),
+            (true, 20_000),
+            (true, 10_000),
+            (true, 15_000),
+            (false, 1_000),
+        ] {
+            stats.add(record(Difficulty::Beginner, won, time_ms));
+        }
+        stats.add(record(Difficulty::Expert, true, 90_000));
+        stats
+    }
+
+    #[test]
+    fn summarises_each_difficulty_on_its_own() {
+        let stats = history();
+        let beginner = stats.summary(Difficulty::Beginner);
+        assert_eq!(beginner.played, 7);
+        assert_eq!(beginner.won, 5);
+        assert_eq!(beginner.best_time, Some(8.0));
+        assert_eq!(beginner.average_time, Some(13.0));
+        assert_eq!(beginner.current_streak, 0);
+        assert_eq!(beginner.longest_streak, 3);
+
+        let expert = stats.summary(Difficulty::Expert);
+        assert_eq!(expert.played, 1);
+        assert_eq!(expert.best_time, Some(90.0));
+        assert_eq!(expert.current_streak, 1);
+
+        let intermediate = stats.summary(Difficulty::Intermediate);
+        assert_eq!(intermediate, Summary::default());
+        assert_eq!(intermediate.win_percent(), 0.0);
+        assert_eq!(
+            stats.difficulties(),
+            vec![Difficulty::Beginner, Difficulty::Expert]
+        );
+    }
+
+    #[test]
+    fn lists_best_times_fastest_first() {
+        let mut stats = history();
+        let tie = stats.add(record(Difficulty::Beginner, true, 8_000));
+        let best: Vec<usize> = stats
+            .best_times(Difficulty::Beginner)
+            .iter()
+            .map(|&(index, _)| index)
+            .collect();
+        assert_eq!(best, vec![1, tie, 4, 0, 5, 3]);
+        assert!(stats.is_best_time(tie));
+        assert!(!stats.is_best_time(2));
+
+        for _ in 0..BEST_TIMES {
+            stats.add(record(Difficulty::Beginner, true, 5_000));
+        }
+        assert_eq!(stats.best_times(Difficulty::Beginner).len(), BEST_TIMES);
+        assert!(!stats.is_best_time(tie));
+    }
+
+    #[test]
+    fn counts_wins_into_time_ranges() {
+        let stats = history();
+        // Slowest win is 20s, so 4 ranges are 6s wide
+        assert_eq!(
+            stats.time_histogram(Difficulty::Beginner, 4),
+            (6, vec![0, 2, 2, 1])
+        );
+        assert_eq!(
+            stats.time_histogram(Difficulty::Intermediate, 3),
+            (1, vec![0, 0, 0])
+        );
+    }
+
+    #[test]
+    fn removing_a_game_moves_later_ones_down() {
+        let mut stats = history();
+        stats.remove(1);
+        assert_eq!(stats.games().len(), 7);
+        assert_eq!(stats.summary(Difficulty::Beginner).best_time, Some(10.0));
+        stats.remove(100);
+        assert_eq!(stats.games().len(), 7);
+    }
+}
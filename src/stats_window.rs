@@ -0,0 +1,289 @@
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+use crate::name_dialog::NameDialog;
+use crate::theme::{Theme, theme};
+use crate::{
+    MinesweeperView, bevel_button, bevel_button_sized, bevel_sunken, dialog_window_options,
+};
+use minesweep::game::Difficulty;
+use minesweep::stats::{Stats, stats_path};
+
+const HISTOGRAM_BUCKETS: usize = 8;
+
+const HISTOGRAM_WIDTH: f32 = 150.0;
+
+pub fn difficulty_name(difficulty: Difficulty) -> String {
+    match difficulty {
+        Difficulty::Beginner => "Beginner".to_string(),
+        Difficulty::Intermediate => "Intermediate".to_string(),
+        Difficulty::Expert => "Expert".to_string(),
+        Difficulty::Custom { rows, cols, mines } => format!("{}x{}/{}", cols, rows, mines),
+    }
+}
+
+// Best times, win rate, streaks and a spread of times for each difficulty played
+pub struct StatsWindow {
+    game_view: Entity<MinesweeperView>,
+    selected: Difficulty,
+    confirm_reset: bool,
+    _observe: Subscription, // Follows games finished while the window is open
+}
+
+impl StatsWindow {
+    pub fn new(
+        selected: Difficulty,
+        game_view: Entity<MinesweeperView>,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        let _observe = cx.observe(&game_view, |_, _, cx| cx.notify());
+        Self {
+            game_view,
+            selected,
+            confirm_reset: false,
+            _observe,
+        }
+    }
+
+    fn select(&mut self, difficulty: Difficulty, cx: &mut Context<Self>) {
+        self.selected = difficulty;
+        self.confirm_reset = false;
+        cx.notify();
+    }
+
+    fn reset(&mut self, cx: &mut Context<Self>) {
+        self.confirm_reset = false;
+        self.game_view.update(cx, |view, cx| view.reset_stats(cx));
+    }
+
+    fn render_summary(&self, stats: &Stats) -> Div {
+        let summary = stats.summary(self.selected);
+        let time = |seconds: Option<f32>| match seconds {
+            Some(seconds) => format!("{:.1}s", seconds),
+            None => "—".to_string(),
+        };
+        div()
+            .flex()
+            .flex_col()
+            .child(format!(
+                "Played {} · Won {} ({:.0}%)",
+                summary.played,
+                summary.won,
+                summary.win_percent()
+            ))
+            .child(format!(
+                "Best {} · Average {}",
+                time(summary.best_time),
+                time(summary.average_time)
+            ))
+            .child(format!(
+                "Current streak {} · Longest {}",
+                summary.current_streak, summary.longest_streak
+            ))
+    }
+
+    fn render_best_times(&self, stats: &Stats) -> Div {
+        let best = stats.best_times(self.selected);
+        let cell = |width: f32, text: String| div().w(px(width)).child(text);
+        div()
+            .flex()
+            .flex_col()
+            .child(div().font_weight(FontWeight::BOLD).child("Best Times"))
+            .when(best.is_empty(), |list| list.child("No wins yet"))
+            .children(best.into_iter().enumerate().map(|(rank, (_, game))| {
+                let date = game
+                    .date()
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                let name = if game.name.is_empty() {
+                    "Anonymous".to_string()
+                } else {
+                    game.name.clone()
+                };
+                div()
+                    .flex()
+                    .child(cell(24.0, format!("{}.", rank + 1)))
+                    .child(cell(50.0, format!("{:.3}s", game.time_ms as f32 / 1000.0)))
+                    .child(cell(130.0, name))
+                    .child(cell(80.0, date))
+            }))
+    }
+
+    fn render_histogram(&self, stats: &Stats, theme: &Theme) -> Div {
+        let (width, counts) = stats.time_histogram(self.selected, HISTOGRAM_BUCKETS);
+        let most = counts.iter().copied().max().unwrap_or(0).max(1);
+        div()
+            .flex()
+            .flex_col()
+            .child(div().font_weight(FontWeight::BOLD).child("Winning Times"))
+            .children(counts.into_iter().enumerate().map(|(bucket, count)| {
+                let from = bucket as u32 * width;
+                div()
+                    .flex()
+                    .items_center()
+                    .gap(px(4.0))
+                    .child(
+                        div()
+                            .w(px(70.0))
+                            .child(format!("{}–{}s", from, from + width - 1)),
+                    )
+                    .child(
+                        div()
+                            .h(px(8.0))
+                            .w(px(HISTOGRAM_WIDTH * count as f32 / most as f32))
+                            .bg(theme.dim_text),
+                    )
+                    .child(count.to_string())
+            }))
+    }
+
+    fn render_reset(&self, cx: &mut Context<Self>) -> Div {
+        let theme = theme(cx).clone();
+        let row = div().flex().items_center().gap(px(6.0));
+        if !self.confirm_reset {
+            return row.child(
+                bevel_button_sized(&theme, "Reset statistics", px(110.0)).on_mouse_down(
+                    MouseButton::Left,
+                    cx.listener(|view, _, _, cx| {
+                        view.confirm_reset = true;
+                        cx.notify();
+                    }),
+                ),
+            );
+        }
+        row.child("Forget every game?")
+            .child(bevel_button(&theme, "Reset").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| view.reset(cx)),
+            ))
+            .child(bevel_button(&theme, "Cancel").on_mouse_down(
+                MouseButton::Left,
+                cx.listener(|view, _, _, cx| {
+                    view.confirm_reset = false;
+                    cx.notify();
+                }),
+            ))
+    }
+}
+
+impl Render for StatsWindow {
+    fn render(&mut self, _window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let theme = theme(cx).clone();
+        let Some(stats) = self.game_view.read(cx).stats.clone() else {
+            return div()
+                .size_full()
+                .bg(theme.face)
+                .text_color(theme.text)
+                .p(px(10.0))
+                .text_size(px(12.0))
+                .child("The statistics file could not be read.")
+                .child(self.render_reset(cx));
+        };
+
+        let mut tabs = vec![
+            Difficulty::Beginner,
+            Difficulty::Intermediate,
+            Difficulty::Expert,
+        ];
+        for difficulty in stats.difficulties() {
+            if !tabs.contains(&difficulty) {
+                tabs.push(difficulty);
+            }
+        }
+
+        div()
+            .size_full()
+            .bg(theme.face)
+            .text_color(theme.text)
+            .p(px(10.0))
+            .flex()
+            .flex_col()
+            .gap(px(8.0))
+            .text_size(px(12.0))
+            .child(
+                div()
+                    .flex()
+                    .flex_wrap()
+                    .gap(px(4.0))
+                    .children(tabs.into_iter().map(|difficulty| {
+                        bevel_button(&theme, difficulty_name(difficulty))
+                            .when(difficulty == self.selected, |tab| {
+                                tab.font_weight(FontWeight::BOLD)
+                            })
+                            .on_mouse_down(
+                                MouseButton::Left,
+                                cx.listener(move |view, _, _, cx| view.select(difficulty, cx)),
+                            )
+                    })),
+            )
+            .child(bevel_sunken(
+                &theme,
+                div()
+                    .flex()
+                    .flex_col()
+                    .gap(px(8.0))
+                    .p(px(6.0))
+                    .child(self.render_summary(&stats))
+                    .child(self.render_best_times(&stats))
+                    .child(self.render_histogram(&stats, &theme)),
+            ))
+            .child(self.render_reset(cx))
+    }
+}
+
+impl MinesweeperView {
+    pub fn open_statistics(&mut self, cx: &mut Context<Self>) {
+        let selected = self.difficulty;
+        let game_view = cx.entity();
+        let options = dialog_window_options("Statistics", size(px(320.0), px(440.0)), cx);
+        cx.open_window(options, |_, cx| {
+            cx.new(|cx| StatsWindow::new(selected, game_view, cx))
+        })
+        .ok();
+    }
+
+    pub fn reset_stats(&mut self, cx: &mut Context<Self>) {
+        let stats = self.stats.get_or_insert_default();
+        stats.clear();
+        self.stats_entry = None;
+        self.save_stats(cx);
+        cx.notify();
+    }
+
+    pub fn set_record_name(&mut self, index: usize, name: &str, cx: &mut Context<Self>) {
+        if let Some(stats) = &mut self.stats {
+            stats.set_name(index, name);
+            self.save_stats(cx);
+            cx.notify();
+        }
+    }
+
+    pub fn save_stats(&mut self, cx: &mut Context<Self>) {
+        if let (Some(stats), Some(path)) = (&self.stats, stats_path())
+            && let Err(err) = stats.save(&path)
+        {
+            self.report_error("Could not save statistics", err, cx);
+        }
+    }
+
+    // Asks who set a new best time, like the classic "Fastest Mine Sweepers"
+    pub fn ask_record_name(&mut self, index: usize, cx: &mut Context<Self>) {
+        let Some(stats) = &self.stats else {
+            return;
+        };
+        let Some(game) = stats.games().get(index) else {
+            return;
+        };
+        let message = format!(
+            "You have one of the fastest times for {}. Please enter your name.",
+            difficulty_name(game.difficulty())
+        );
+        let current = stats.player().to_string();
+        let game_view = cx.entity().downgrade();
+        let options = dialog_window_options("New Best Time", size(px(240.0), px(120.0)), cx);
+        cx.open_window(options, |window, cx| {
+            cx.new(|cx| NameDialog::new(message.into(), &current, index, game_view, window, cx))
+        })
+        .ok();
+    }
+}
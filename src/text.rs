@@ -0,0 +1,432 @@
+use std::fmt;
+
+use crate::game::{
+    Cell, CellContent, CellState, ConfigError, Difficulty, GameStatus, MAX_CELL_MINES, Minesweeper,
+};
+use crate::topology::{Grid, Neighborhood};
+
+// Plain-text boards for bug reports, puzzles and tests. A header line, then
+// one line of characters per row:
+//
+//   minesweeper mines=10 status=playing flags=1 seed=42 grid=square wrap=false rule=classic
+//   ..1#####
+//   ..1F####
+//
+// Hidden:   `#` safe, `M` mine
+// Flagged:  `F` on a mine, `f` on a safe cell
+// Marked ?: `?` safe, `Q` mine
+// Revealed: `.` empty, `1`-`9` number, `+` number of 10 or more, `*` mine,
+//           `X` exploded mine, `x` wrong flag shown after a loss
+//
+// Numbers are worked out from the mines, so a revealed digit that disagrees
+// with its neighbours is rejected, as is a flag count that disagrees with
+// the flags of a game still open. A board without mines is one whose first
+// click hasn't happened yet. On a hex grid (`grid=hex`) odd rows sit half a
+// cell to the right, but the text still has one plain line per row.
+//
+// Multi-mine boards (`multi=true`) keep the same characters and list the
+// cells holding more than one mine as `stacks=index:count,...`, and the cells
+// with more than one flag as `flag_stacks=index:count,...`, indices counting
+// row by row from 0.
+
+const MAGIC: &str = "minesweeper";
+
+// Numbers this big or bigger share the `+` character
+const MANY: u8 = 10;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum TextError {
+    MissingHeader,
+    BadHeader(String),
+    Ragged { row: u32 },
+    UnknownCell { row: u32, col: u32, found: char },
+    NumberMismatch { row: u32, col: u32 },
+    MineCount { header: u32, grid: u32 },
+    BadStack { index: usize },
+    StartedWithoutMines,
+    FlagCount { header: u32, board: u32 },
+    Config(ConfigError),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::MissingHeader => write!(f, "Board text must start with a '{}' line", MAGIC),
+            TextError::BadHeader(field) => write!(f, "Unrecognised header field '{}'", field),
+            TextError::Ragged { row } => {
+                write!(f, "Row {} is not as long as the first row", row + 1)
+            }
+            TextError::UnknownCell { row, col, found } => {
+                write!(
+                    f,
+                    "Unknown cell '{}' at row {}, column {}",
+                    found,
+                    row + 1,
+                    col + 1
+                )
+            }
+            TextError::NumberMismatch { row, col } => write!(
+                f,
+                "Number at row {}, column {} does not match its mines",
+                row + 1,
+                col + 1
+            ),
+            TextError::MineCount { header, grid } => {
+                write!(f, "Header says {} mines but the board has {}", header, grid)
+            }
+            TextError::BadStack { index } => {
+                write!(f, "Cell {} cannot hold that stack", index)
+            }
+            TextError::StartedWithoutMines => {
+                write!(f, "A game in play or over must show where its mines are")
+            }
+            TextError::FlagCount { header, board } => {
+                write!(
+                    f,
+                    "Header says {} flags but the board has {}",
+                    header, board
+                )
+            }
+            TextError::Config(err) => write!(f, "Invalid board: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+fn cell_char(cell: &Cell) -> char {
+    let mine = cell.content.is_mine();
+    match cell.state {
+        CellState::Hidden if mine => 'M',
+        CellState::Hidden => '#',
+        CellState::Flagged(_) if mine => 'F',
+        CellState::Flagged(_) => 'f',
+        CellState::QuestionMark if mine => 'Q',
+        CellState::QuestionMark => '?',
+        CellState::Revealed if cell.wrong_flag => 'x',
+        CellState::Revealed => match cell.content {
+            CellContent::Mine(_) if cell.exploded => 'X',
+            CellContent::Mine(_) => '*',
+            CellContent::Empty => '.',
+            CellContent::Number(n) if n >= MANY => '+',
+            CellContent::Number(n) => char::from_digit(n as u32, 10).unwrap_or('?'),
+        },
+    }
+}
+
+// State, whether it is a mine, and the revealed number to check (0 for `.`,
+// MANY for `+`)
+fn parse_cell(c: char) -> Option<(Cell, bool, Option<u8>)> {
+    let mut cell = Cell::new();
+    let (state, mine, digit) = match c {
+        '#' => (CellState::Hidden, false, None),
+        'M' => (CellState::Hidden, true, None),
+        'F' => (CellState::Flagged(1), true, None),
+        'f' => (CellState::Flagged(1), false, None),
+        '?' => (CellState::QuestionMark, false, None),
+        'Q' => (CellState::QuestionMark, true, None),
+        '.' => (CellState::Revealed, false, Some(0)),
+        '1'..='9' => (CellState::Revealed, false, c.to_digit(10).map(|d| d as u8)),
+        '+' => (CellState::Revealed, false, Some(MANY)),
+        '*' => (CellState::Revealed, true, None),
+        'X' => {
+            cell.exploded = true;
+            (CellState::Revealed, true, None)
+        }
+        'x' => {
+            cell.wrong_flag = true;
+            (CellState::Revealed, false, None)
+        }
+        _ => return None,
+    };
+    cell.state = state;
+    Some((cell, mine, digit))
+}
+
+// `index:count` pairs for the cells where `count` is above 1
+fn stacks_text(counts: impl Iterator<Item = u8>) -> String {
+    counts
+        .enumerate()
+        .filter(|&(_, count)| count > 1)
+        .map(|(index, count)| format!("{}:{}", index, count))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn parse_stacks(value: &str) -> Option<Vec<(usize, u8)>> {
+    value
+        .split(',')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (index, count) = pair.split_once(':')?;
+            Some((index.parse().ok()?, count.parse().ok()?))
+        })
+        .collect()
+}
+
+fn status_name(status: GameStatus) -> &'static str {
+    match status {
+        GameStatus::NotStarted => "not_started",
+        GameStatus::Playing => "playing",
+        GameStatus::Won => "won",
+        GameStatus::Lost => "lost",
+    }
+}
+
+fn parse_status(name: &str) -> Option<GameStatus> {
+    [
+        GameStatus::NotStarted,
+        GameStatus::Playing,
+        GameStatus::Won,
+        GameStatus::Lost,
+    ]
+    .into_iter()
+    .find(|&status| status_name(status) == name)
+}
+
+impl Minesweeper {
+    /// The board as text, see the format above. `from_text` reads it back
+    /// with the same cells, status and flag count.
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{} mines={} status={} flags={} seed={} grid={} wrap={} rule={}",
+            MAGIC,
+            self.mines,
+            status_name(self.status),
+            self.flags_placed,
+            self.seed,
+            self.grid.name(),
+            self.wrap,
+            self.rule.name()
+        );
+        if self.multi_mine {
+            text.push_str(&format!(
+                " multi=true stacks={} flag_stacks={}",
+                stacks_text(self.cells.iter().map(|cell| cell.content.mines())),
+                stacks_text(self.cells.iter().map(|cell| cell.state.flags()))
+            ));
+        }
+        text.push('\n');
+        for row in self.cells.chunks(self.cols as usize) {
+            text.extend(row.iter().map(cell_char));
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, TextError> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut header = lines
+            .next()
+            .ok_or(TextError::MissingHeader)?
+            .split_whitespace();
+        if header.next() != Some(MAGIC) {
+            return Err(TextError::MissingHeader);
+        }
+        let (mut mines, mut status, mut flags, mut seed) = (None, None, None, 0);
+        let (mut grid, mut wrap, mut rule) = (Grid::Square, false, Neighborhood::Classic);
+        let (mut multi_mine, mut stacks, mut flag_stacks) = (false, Vec::new(), Vec::new());
+        for field in header {
+            let bad = || TextError::BadHeader(field.to_string());
+            let (key, value) = field.split_once('=').ok_or_else(bad)?;
+            match key {
+                "mines" => mines = Some(value.parse().map_err(|_| bad())?),
+                "status" => status = Some(parse_status(value).ok_or_else(bad)?),
+                "flags" => flags = Some(value.parse().map_err(|_| bad())?),
+                "seed" => seed = value.parse().map_err(|_| bad())?,
+                "grid" => grid = Grid::from_name(value).ok_or_else(bad)?,
+                "wrap" => wrap = value.parse().map_err(|_| bad())?,
+                "rule" => rule = Neighborhood::from_name(value).ok_or_else(bad)?,
+                "multi" => multi_mine = value.parse().map_err(|_| bad())?,
+                "stacks" => stacks = parse_stacks(value).ok_or_else(bad)?,
+                "flag_stacks" => flag_stacks = parse_stacks(value).ok_or_else(bad)?,
+                _ => return Err(bad()),
+            }
+        }
+
+        let mut cells = Vec::new();
+        let mut mine_cells = Vec::new();
+        let mut digits = Vec::new();
+        let mut cols = 0;
+        let mut rows = 0;
+        for (row, line) in lines.enumerate() {
+            let row = row as u32;
+            let width = line.chars().count() as u32;
+            if row == 0 {
+                cols = width;
+            } else if width != cols {
+                return Err(TextError::Ragged { row });
+            }
+            for (col, c) in line.chars().enumerate() {
+                let col = col as u32;
+                let (cell, mine, digit) =
+                    parse_cell(c).ok_or(TextError::UnknownCell { row, col, found: c })?;
+                if mine {
+                    mine_cells.push(cells.len());
+                }
+                if let Some(digit) = digit {
+                    digits.push((row, col, digit));
+                }
+                cells.push(cell);
+            }
+            rows += 1;
+        }
+
+        let mut flag_counts = vec![1; cells.len()];
+        for (index, count) in stacks {
+            let valid = multi_mine && (2..=MAX_CELL_MINES).contains(&count);
+            if !valid || !mine_cells.contains(&index) {
+                return Err(TextError::BadStack { index });
+            }
+            mine_cells.extend(std::iter::repeat_n(index, count as usize - 1));
+        }
+        for (index, count) in flag_stacks {
+            let valid = multi_mine && (2..=MAX_CELL_MINES).contains(&count);
+            if !valid || !cells.get(index).is_some_and(|cell| cell.state.is_flagged()) {
+                return Err(TextError::BadStack { index });
+            }
+            flag_counts[index] = count;
+        }
+
+        let grid_mines = mine_cells.len() as u32;
+        // Mines are laid by the first click, so a started game must list them
+        if grid_mines == 0 && status.is_some_and(|status| status != GameStatus::NotStarted) {
+            return Err(TextError::StartedWithoutMines);
+        }
+        let mines = mines.unwrap_or(grid_mines);
+        if grid_mines > 0 && grid_mines != mines {
+            return Err(TextError::MineCount {
+                header: mines,
+                grid: grid_mines,
+            });
+        }
+        let difficulty = Difficulty::from_config(rows, cols, mines);
+        difficulty.validate().map_err(TextError::Config)?;
+
+        let mut game = Minesweeper::with_seed(difficulty, seed);
+        game.grid = grid;
+        game.wrap = wrap;
+        game.rule = rule;
+        game.multi_mine = multi_mine;
+        game.imported = true;
+        game.set_layout(&mine_cells);
+        for ((target, parsed), flags) in game.cells.iter_mut().zip(cells).zip(flag_counts) {
+            target.state = match parsed.state {
+                CellState::Flagged(_) => CellState::Flagged(flags),
+                state => state,
+            };
+            target.exploded = parsed.exploded;
+            target.wrong_flag = parsed.wrong_flag;
+        }
+        for (row, col, digit) in digits {
+            let shown = match game.cells[game.index(row, col)].content {
+                CellContent::Number(n) => n,
+                _ => 0,
+            };
+            if shown.min(MANY) != digit {
+                return Err(TextError::NumberMismatch { row, col });
+            }
+        }
+        game.status = status.unwrap_or(if grid_mines > 0 {
+            GameStatus::Playing
+        } else {
+            GameStatus::NotStarted
+        });
+        game.flags_placed = flags.unwrap_or_else(|| game.flags_on_board());
+        if !game.flag_count_matches() {
+            return Err(TextError::FlagCount {
+                header: game.flags_placed,
+                board: game.flags_on_board(),
+            });
+        }
+        // Moves from here on can't be replayed from an untouched board
+        let untouched = game
+            .cells
+            .iter()
+            .all(|cell| cell.state == CellState::Hidden);
+        if game.status != GameStatus::NotStarted || !untouched {
+            game.start_recording_midway();
+        }
+        Ok(game)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Parses the text of `game` and checks that it comes back the same
+    fn assert_round_trip(game: &Minesweeper) {
+        let text = game.to_text();
+        let parsed = Minesweeper::from_text(&text).unwrap();
+        assert_eq!(parsed.to_text(), text);
+        assert_eq!(parsed.cells, game.cells);
+        assert_eq!(parsed.status, game.status);
+        assert_eq!(parsed.flags_placed, game.flags_placed);
+    }
+
+    fn started() -> Minesweeper {
+        let mut game = Minesweeper::with_seed(Difficulty::Beginner, 42);
+        game.reveal(4, 4).unwrap();
+        game
+    }
+
+    #[test]
+    fn round_trips_a_game_in_play() {
+        let mut game = started();
+        let mine = game.mine_layout()[0] as u32;
+        let hidden = (0..game.cells.len() as u32)
+            .find(|&i| game.cells[i as usize].state == CellState::Hidden && i != mine)
+            .unwrap();
+        game.toggle_flag(mine / 9, mine % 9).unwrap();
+        game.toggle_flag(hidden / 9, hidden % 9).unwrap();
+        game.toggle_flag(hidden / 9, hidden % 9).unwrap();
+        assert_round_trip(&game);
+    }
+
+    #[test]
+    fn round_trips_a_lost_game() {
+        let mut game = started();
+        let mine = game.mine_layout()[0] as u32;
+        game.reveal(mine / 9, mine % 9).unwrap();
+        assert_eq!(game.status, GameStatus::Lost);
+        assert_round_trip(&game);
+    }
+
+    #[test]
+    fn round_trips_variants() {
+        let text = "minesweeper mines=3 grid=hex wrap=true rule=cross multi=true stacks=0:2 flag_stacks=0:2\nF###\n#M##\n####";
+        let game = Minesweeper::from_text(text).unwrap();
+        assert_eq!(game.mine_layout(), vec![0, 0, 5]);
+        assert_eq!(game.flags_placed, 2);
+        assert_round_trip(&game);
+    }
+
+    #[test]
+    fn round_trips_an_untouched_board() {
+        assert_round_trip(&Minesweeper::with_seed(Difficulty::Beginner, 7));
+    }
+
+    #[test]
+    fn rejects_a_flag_count_the_board_disagrees_with() {
+        let text = "minesweeper mines=1 status=playing flags=0\nF1.";
+        assert_eq!(
+            Minesweeper::from_text(text).err(),
+            Some(TextError::FlagCount {
+                header: 0,
+                board: 1
+            })
+        );
+        let game = Minesweeper::from_text("minesweeper mines=1 status=playing\nF1.").unwrap();
+        assert_eq!(game.flags_placed, 1);
+    }
+
+    #[test]
+    fn rejects_a_started_game_without_mines() {
+        let text = "minesweeper mines=1 status=playing\n###";
+        assert_eq!(
+            Minesweeper::from_text(text).err(),
+            Some(TextError::StartedWithoutMines)
+        );
+    }
+}
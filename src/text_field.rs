@@ -0,0 +1,109 @@
+use gpui::prelude::FluentBuilder;
+use gpui::*;
+
+use crate::theme::theme;
+
+// Single-line input used by the dialogs. Only accepts characters passing `filter`.
+pub struct TextField {
+    focus_handle: FocusHandle,
+    text: String,
+    max_len: usize,
+    filter: fn(char) -> bool,
+    width: Pixels,
+}
+
+impl TextField {
+    pub fn new(
+        text: impl Into<String>,
+        max_len: usize,
+        filter: fn(char) -> bool,
+        cx: &mut Context<Self>,
+    ) -> Self {
+        Self {
+            focus_handle: cx.focus_handle().tab_stop(true),
+            text: text.into(),
+            max_len,
+            filter,
+            width: px(60.0),
+        }
+    }
+
+    pub fn numeric(value: impl ToString, max_len: usize, cx: &mut Context<Self>) -> Self {
+        Self::new(value.to_string(), max_len, |c| c.is_ascii_digit(), cx)
+    }
+
+    pub fn with_width(mut self, width: Pixels) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    fn handle_key_down(&mut self, event: &KeyDownEvent, _: &mut Window, cx: &mut Context<Self>) {
+        let keystroke = &event.keystroke;
+        if keystroke.modifiers.control || keystroke.modifiers.platform || keystroke.modifiers.alt {
+            return;
+        }
+        if keystroke.key == "backspace" {
+            self.text.pop();
+            cx.stop_propagation();
+            cx.notify();
+            return;
+        }
+        let Some(typed) = &keystroke.key_char else {
+            return;
+        };
+        let mut accepted = false;
+        for c in typed.chars().filter(|&c| (self.filter)(c)) {
+            accepted = true;
+            if self.text.chars().count() < self.max_len {
+                self.text.push(c);
+            }
+        }
+        if accepted {
+            cx.stop_propagation();
+            cx.notify();
+        }
+    }
+}
+
+impl Focusable for TextField {
+    fn focus_handle(&self, _: &App) -> FocusHandle {
+        self.focus_handle.clone()
+    }
+}
+
+impl Render for TextField {
+    fn render(&mut self, window: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let focused = self.focus_handle.is_focused(window);
+        let theme = theme(cx).clone();
+
+        // Sunken 1px bevel like the counters, around the edit area
+        div().bg(theme.highlight).pb(px(1.0)).pr(px(1.0)).child(
+            div().bg(theme.shadow).pt(px(1.0)).pl(px(1.0)).child(
+                div()
+                    .track_focus(&self.focus_handle)
+                    .on_key_down(cx.listener(Self::handle_key_down))
+                    .on_mouse_down(
+                        MouseButton::Left,
+                        cx.listener(|view, _, window, _| view.focus_handle.focus(window)),
+                    )
+                    .w(self.width)
+                    .h(px(18.0))
+                    .px(px(3.0))
+                    .flex()
+                    .items_center()
+                    .bg(theme.field)
+                    .text_size(px(12.0))
+                    .text_color(theme.text)
+                    .child(self.text.clone())
+                    // Caret
+                    .when(focused, |field| {
+                        field.child(div().w(px(1.0)).h(px(13.0)).bg(theme.text))
+                    }),
+            ),
+        )
+    }
+}
@@ -0,0 +1,169 @@
+use gpui::*;
+
+use crate::{MinesweeperView, app_menus};
+use minesweep::settings::{ThemeName, settings_path};
+
+/// Every color the game is drawn with. The one in use is a global, so all
+/// windows pick up a change of theme.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    pub face: Rgba,      // Windows, panels, buttons and hidden cells
+    pub highlight: Rgba, // Lit top and left edges of a bevel
+    pub shadow: Rgba,    // Shaded bottom and right edges, and grid lines
+    pub text: Rgba,
+    pub dim_text: Rgba,
+    pub revealed: Rgba,
+    pub counter: Rgba, // Behind the mine counter and timer
+    pub counter_text: Rgba,
+    pub error_text: Rgba,
+    pub exploded: Rgba, // Behind the mine that was hit
+    pub field: Rgba,    // Inside text fields
+    pub cursor: Rgba,
+    pub wrap_edge: Rgba,
+    pub hint_safe: Rgba,
+    pub hint_guess: Rgba,
+    numbers: [Rgba; 13], // 1 to 12, then the rest
+}
+
+impl Global for Theme {}
+
+fn palette(numbers: [u32; 13]) -> [Rgba; 13] {
+    numbers.map(rgba)
+}
+
+impl Theme {
+    pub fn named(name: ThemeName) -> Self {
+        match name {
+            ThemeName::Win2000 => Self::win2000(),
+            ThemeName::Xp => Self::xp(),
+            ThemeName::Dark => Self::dark(),
+            ThemeName::HighContrast => Self::high_contrast(),
+        }
+    }
+
+    fn win2000() -> Self {
+        Self {
+            face: rgba(0xC0C0C0FF),
+            highlight: rgba(0xFFFFFFFF),
+            shadow: rgba(0x808080FF),
+            text: rgba(0x000000FF),
+            dim_text: rgba(0x808080FF),
+            revealed: rgba(0xC0C0C0FF),
+            counter: rgba(0x000000FF),
+            counter_text: rgba(0xFF0000FF),
+            error_text: rgba(0xFF0000FF),
+            exploded: rgba(0xFF0000FF),
+            field: rgba(0xFFFFFFFF),
+            cursor: rgba(0x000000C0),
+            wrap_edge: rgba(0x0000FF60),
+            hint_safe: rgba(0x00C000FF),
+            hint_guess: rgba(0xFF8000FF),
+            numbers: palette([
+                0x0000FFFF, // Blue
+                0x008000FF, // Green
+                0xFF0000FF, // Red
+                0x000080FF, // Dark Blue
+                0x800000FF, // Maroon
+                0x008080FF, // Teal
+                0x000000FF, // Black
+                0x808080FF, // Gray
+                // Only wider neighbourhoods get this far
+                0x800080FF, // Purple
+                0x808000FF, // Olive
+                0xC06000FF, // Orange
+                0xC00060FF, // Pink
+                0x400040FF, // Dark purple for the rest
+            ]),
+        }
+    }
+
+    // Luna's beige face with the classic numbers
+    fn xp() -> Self {
+        Self {
+            face: rgba(0xECE9D8FF),
+            highlight: rgba(0xFFFFFFFF),
+            shadow: rgba(0xACA899FF),
+            dim_text: rgba(0xACA899FF),
+            revealed: rgba(0xF5F4EEFF),
+            error_text: rgba(0xC00000FF),
+            cursor: rgba(0x0054E3C0),
+            wrap_edge: rgba(0x0054E360),
+            ..Self::win2000()
+        }
+    }
+
+    fn dark() -> Self {
+        Self {
+            face: rgba(0x3C3F41FF),
+            highlight: rgba(0x5E6265FF),
+            shadow: rgba(0x1E1F20FF),
+            text: rgba(0xE0E0E0FF),
+            dim_text: rgba(0x909090FF),
+            revealed: rgba(0x2B2B2BFF),
+            counter: rgba(0x101010FF),
+            counter_text: rgba(0xFF5050FF),
+            error_text: rgba(0xFF6060FF),
+            exploded: rgba(0xB02020FF),
+            field: rgba(0x2B2B2BFF),
+            cursor: rgba(0xFFFFFFC0),
+            wrap_edge: rgba(0x80A0FF60),
+            hint_safe: rgba(0x40D040FF),
+            hint_guess: rgba(0xFFA040FF),
+            // Lighter shades of the classic colors, to read on dark cells
+            numbers: palette([
+                0x6CA0FFFF, 0x6CC070FF, 0xFF6B6BFF, 0xA08CFFFF, 0xE08060FF, 0x50C8C8FF, 0xE0E0E0FF,
+                0xA0A0A0FF, 0xD080D0FF, 0xC0C060FF, 0xFFA040FF, 0xFF70B0FF, 0xC0A0C0FF,
+            ]),
+        }
+    }
+
+    // White edges on black, with numbers in fully saturated colors
+    fn high_contrast() -> Self {
+        Self {
+            face: rgba(0x000000FF),
+            highlight: rgba(0xFFFFFFFF),
+            shadow: rgba(0x808080FF),
+            text: rgba(0xFFFFFFFF),
+            dim_text: rgba(0xFFFFFFFF),
+            revealed: rgba(0x000000FF),
+            counter: rgba(0x000000FF),
+            counter_text: rgba(0xFFFF00FF),
+            error_text: rgba(0xFFFF00FF),
+            exploded: rgba(0xFF0000FF),
+            field: rgba(0x000000FF),
+            cursor: rgba(0x00FFFFFF),
+            wrap_edge: rgba(0x00FFFFFF),
+            hint_safe: rgba(0x00FF00FF),
+            hint_guess: rgba(0xFF8000FF),
+            numbers: palette([
+                0x00FFFFFF, 0x00FF00FF, 0xFF4040FF, 0xFFFF00FF, 0xFF80FFFF, 0x80C0FFFF, 0xFFFFFFFF,
+                0xC0C0C0FF, 0xFF8000FF, 0xC0FF80FF, 0xFFC080FF, 0xFF80C0FF, 0xFFFFFFFF,
+            ]),
+        }
+    }
+
+    pub fn number(&self, n: u8) -> Rgba {
+        self.numbers[n.clamp(1, 13) as usize - 1]
+    }
+}
+
+/// The theme in use.
+pub fn theme(cx: &App) -> &Theme {
+    cx.global::<Theme>()
+}
+
+impl MinesweeperView {
+    /// Switches every window to `name` and remembers it for the next launch.
+    pub fn set_theme(&mut self, name: ThemeName, cx: &mut Context<Self>) {
+        self.settings.theme = name;
+        if let Some(path) = settings_path()
+            && let Err(err) = self.settings.save(&path)
+        {
+            self.report_error("Could not save settings", err, cx);
+        }
+        cx.set_global(Theme::named(name));
+        cx.set_menus(app_menus(self));
+        // Dialogs and the statistics window are drawn from the theme too
+        cx.refresh_windows();
+    }
+}
@@ -0,0 +1,376 @@
+use serde::{Deserialize, Serialize};
+
+/// The geometry of a board: which cells touch which. Cells are always
+/// stored row by row; only adjacency differs between topologies, so flood
+/// fill, chording, numbers and the solver all follow `neighbors`.
+pub trait Topology {
+    /// Row and column steps from a cell in `row` to each cell touching it,
+    /// in a fixed order.
+    fn offsets(&self, row: u32) -> &'static [(i32, i32)];
+
+    /// Whether the top and bottom rows can meet when the board wraps.
+    fn wraps_rows(&self, _rows: u32) -> bool {
+        true
+    }
+
+    /// Cells touching (`row`, `col`) on a `rows` x `cols` board. With `wrap`
+    /// the board is a torus: opposite edges touch, so edge cells have as many
+    /// neighbours as any other (fewer only on boards too small to hold them).
+    fn neighbors(&self, rows: u32, cols: u32, row: u32, col: u32, wrap: bool) -> Vec<(u32, u32)> {
+        let wrap_rows = wrap && self.wraps_rows(rows);
+        let offsets = self.offsets(row);
+        let mut neighbors = Vec::with_capacity(offsets.len());
+        for &(dr, dc) in offsets {
+            let (Some(nr), Some(nc)) = (step(row, dr, rows, wrap_rows), step(col, dc, cols, wrap))
+            else {
+                continue;
+            };
+            if (nr, nc) != (row, col) && !neighbors.contains(&(nr, nc)) {
+                neighbors.push((nr, nc));
+            }
+        }
+        neighbors
+    }
+
+    fn index(&self, cols: u32, row: u32, col: u32) -> usize {
+        (row * cols + col) as usize
+    }
+}
+
+// Moves along one axis, going round the far edge when wrapping
+fn step(pos: u32, delta: i32, len: u32, wrap: bool) -> Option<u32> {
+    let moved = pos as i32 + delta;
+    if wrap {
+        Some(moved.rem_euclid(len as i32) as u32)
+    } else {
+        (0..len as i32).contains(&moved).then_some(moved as u32)
+    }
+}
+
+/// The classic grid: every cell touches the 8 around it.
+pub struct Square;
+
+// Row by row, as shuffling the no-guess safe area depends on this order
+const SQUARE: [(i32, i32); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+impl Topology for Square {
+    fn offsets(&self, _row: u32) -> &'static [(i32, i32)] {
+        &SQUARE
+    }
+}
+
+/// Square cells touching only along an edge: up, down, left and right.
+pub struct Cross;
+
+const CROSS: [(i32, i32); 4] = [(-1, 0), (0, -1), (0, 1), (1, 0)];
+
+impl Topology for Cross {
+    fn offsets(&self, _row: u32) -> &'static [(i32, i32)] {
+        &CROSS
+    }
+}
+
+/// Square cells touching the 8 a chess knight can jump to.
+pub struct Knight;
+
+const KNIGHT: [(i32, i32); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+impl Topology for Knight {
+    fn offsets(&self, _row: u32) -> &'static [(i32, i32)] {
+        &KNIGHT
+    }
+}
+
+/// Square cells touching the 24 within two steps in any direction.
+pub struct Radius2;
+
+const RADIUS_2: [(i32, i32); 24] = {
+    let mut offsets = [(0, 0); 24];
+    let mut i = 0;
+    let mut dr = -2;
+    while dr <= 2 {
+        let mut dc = -2;
+        while dc <= 2 {
+            if dr != 0 || dc != 0 {
+                offsets[i] = (dr, dc);
+                i += 1;
+            }
+            dc += 1;
+        }
+        dr += 1;
+    }
+    offsets
+};
+
+impl Topology for Radius2 {
+    fn offsets(&self, _row: u32) -> &'static [(i32, i32)] {
+        &RADIUS_2
+    }
+}
+
+/// Hexagons in offset rows: odd rows sit half a cell to the right, and every
+/// cell touches 6 others.
+pub struct Hex;
+
+const HEX_EVEN_ROW: [(i32, i32); 6] = [(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)];
+const HEX_ODD_ROW: [(i32, i32); 6] = [(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)];
+
+impl Topology for Hex {
+    fn offsets(&self, row: u32) -> &'static [(i32, i32)] {
+        if row.is_multiple_of(2) {
+            &HEX_EVEN_ROW
+        } else {
+            &HEX_ODD_ROW
+        }
+    }
+
+    // With an odd number of rows the last and first would both be unshifted,
+    // so such boards only wrap left to right
+    fn wraps_rows(&self, rows: u32) -> bool {
+        rows.is_multiple_of(2)
+    }
+}
+
+/// Which topology a game is played on; kept with saved games and replays.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Grid {
+    #[default]
+    Square,
+    Hex,
+}
+
+impl Grid {
+    /// The topology for this grid under a neighbourhood rule. Hex cells
+    /// always touch their 6 neighbours, so the rule only applies to squares.
+    pub fn topology(self, rule: Neighborhood) -> &'static dyn Topology {
+        match (self, rule) {
+            (Grid::Square, Neighborhood::Classic) => &Square,
+            (Grid::Square, Neighborhood::Cross) => &Cross,
+            (Grid::Square, Neighborhood::Knight) => &Knight,
+            (Grid::Square, Neighborhood::Radius2) => &Radius2,
+            (Grid::Hex, _) => &Hex,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Grid::Square => "square",
+            Grid::Hex => "hex",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        [Grid::Square, Grid::Hex]
+            .into_iter()
+            .find(|grid| grid.name() == name)
+    }
+}
+
+/// Which cells count as touching on a square grid, for numbers, flood fill
+/// and chording alike.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Neighborhood {
+    #[default]
+    Classic,
+    Cross,
+    Knight,
+    Radius2,
+}
+
+impl Neighborhood {
+    pub const ALL: [Neighborhood; 4] = [
+        Neighborhood::Classic,
+        Neighborhood::Cross,
+        Neighborhood::Knight,
+        Neighborhood::Radius2,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Neighborhood::Classic => "classic",
+            Neighborhood::Cross => "cross",
+            Neighborhood::Knight => "knight",
+            Neighborhood::Radius2 => "radius2",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|rule| rule.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{CellContent, Difficulty, Minesweeper};
+
+    fn neighbors(topology: &dyn Topology, size: (u32, u32), cell: (u32, u32)) -> Vec<(u32, u32)> {
+        topology.neighbors(size.0, size.1, cell.0, cell.1, false)
+    }
+
+    #[test]
+    fn square_cells_touch_the_eight_around_them() {
+        assert_eq!(neighbors(&Square, (3, 3), (1, 1)).len(), 8);
+        assert_eq!(
+            neighbors(&Square, (3, 3), (0, 0)),
+            vec![(0, 1), (1, 0), (1, 1)]
+        );
+    }
+
+    #[test]
+    fn hex_rows_alternate_their_offsets() {
+        // Odd rows sit half a cell right, so they reach the column after
+        assert_eq!(
+            neighbors(&Hex, (4, 4), (1, 1)),
+            vec![(0, 1), (0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]
+        );
+        assert_eq!(
+            neighbors(&Hex, (4, 4), (2, 1)),
+            vec![(1, 0), (1, 1), (2, 0), (2, 2), (3, 0), (3, 1)]
+        );
+        assert_eq!(neighbors(&Hex, (4, 4), (0, 0)), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn hex_grids_ignore_the_rule() {
+        for rule in Neighborhood::ALL {
+            let topology = Grid::Hex.topology(rule);
+            assert_eq!(neighbors(topology, (4, 4), (1, 1)).len(), 6);
+        }
+    }
+
+    fn wrapped(topology: &dyn Topology, size: (u32, u32), cell: (u32, u32)) -> Vec<(u32, u32)> {
+        topology.neighbors(size.0, size.1, cell.0, cell.1, true)
+    }
+
+    #[test]
+    fn wrapped_edges_touch_the_far_side() {
+        assert_eq!(
+            wrapped(&Square, (4, 4), (0, 0)),
+            vec![
+                (3, 3),
+                (3, 0),
+                (3, 1),
+                (0, 3),
+                (0, 1),
+                (1, 3),
+                (1, 0),
+                (1, 1)
+            ]
+        );
+        // On 3x3 every other cell is next to the corner
+        assert_eq!(wrapped(&Square, (3, 3), (0, 0)).len(), 8);
+    }
+
+    #[test]
+    fn tiny_wrapped_boards_list_each_neighbour_once() {
+        assert_eq!(
+            wrapped(&Square, (2, 2), (0, 0)),
+            vec![(1, 1), (1, 0), (0, 1)]
+        );
+        assert_eq!(wrapped(&Square, (1, 3), (0, 1)), vec![(0, 0), (0, 2)]);
+        assert_eq!(wrapped(&Square, (1, 2), (0, 0)), vec![(0, 1)]);
+        assert_eq!(wrapped(&Square, (1, 1), (0, 0)), vec![]);
+
+        let topologies: [&dyn Topology; 5] = [&Square, &Cross, &Knight, &Radius2, &Hex];
+        for topology in topologies {
+            for (rows, cols) in [(1, 1), (1, 2), (2, 1), (2, 2), (2, 3), (3, 2)] {
+                for (row, col) in (0..rows).flat_map(|r| (0..cols).map(move |c| (r, c))) {
+                    let found = wrapped(topology, (rows, cols), (row, col));
+                    assert!(!found.contains(&(row, col)));
+                    for (i, cell) in found.iter().enumerate() {
+                        assert!(!found[i + 1..].contains(cell), "{:?} repeated", cell);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn hex_boards_only_wrap_rows_when_there_are_an_even_number() {
+        assert_eq!(
+            wrapped(&Hex, (4, 4), (0, 0)),
+            vec![(3, 3), (3, 0), (0, 3), (0, 1), (1, 3), (1, 0)]
+        );
+        assert_eq!(
+            wrapped(&Hex, (3, 4), (0, 0)),
+            vec![(0, 3), (0, 1), (1, 3), (1, 0)]
+        );
+    }
+
+    #[test]
+    fn cross_cells_touch_along_edges_only() {
+        assert_eq!(
+            neighbors(&Cross, (3, 3), (1, 1)),
+            vec![(0, 1), (1, 0), (1, 2), (2, 1)]
+        );
+        assert_eq!(neighbors(&Cross, (3, 3), (0, 0)), vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn knight_cells_touch_a_knight_move_away() {
+        assert_eq!(
+            neighbors(&Knight, (5, 5), (2, 2)),
+            vec![
+                (0, 1),
+                (0, 3),
+                (1, 0),
+                (1, 4),
+                (3, 0),
+                (3, 4),
+                (4, 1),
+                (4, 3)
+            ]
+        );
+        assert_eq!(neighbors(&Knight, (5, 5), (0, 0)), vec![(1, 2), (2, 1)]);
+    }
+
+    #[test]
+    fn radius_2_cells_touch_everything_two_steps_away() {
+        assert_eq!(neighbors(&Radius2, (5, 5), (2, 2)).len(), 24);
+        assert_eq!(
+            neighbors(&Radius2, (5, 5), (0, 0)),
+            vec![
+                (0, 1),
+                (0, 2),
+                (1, 0),
+                (1, 1),
+                (1, 2),
+                (2, 0),
+                (2, 1),
+                (2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn radius_2_numbers_go_above_8() {
+        // Every cell but the centre of a 5x5 board is a mine
+        let mut game = Minesweeper::new(Difficulty::custom(5, 5, 24).unwrap());
+        game.rule = Neighborhood::Radius2;
+        game.set_layout(&(0..25).filter(|&i| i != 12).collect::<Vec<_>>());
+        assert_eq!(game.cells[12].content, CellContent::Number(24));
+        game.reveal(2, 2).unwrap();
+        assert!(game.to_text().lines().nth(3).unwrap().contains('+'));
+    }
+}